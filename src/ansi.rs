@@ -0,0 +1,210 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// Lines of `text` with its SGR colours and attributes turned into styles.
+/// A style carries over from one line to the next, as `capture-pane -e`
+/// only emits changes; every other escape sequence and control character is
+/// dropped.
+pub fn parse(text: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut style = Style::default();
+    let mut chars = text.chars().peekable();
+
+    let flush = |spans: &mut Vec<Span<'static>>, run: &mut String, style: Style| {
+        if !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(run), style));
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                flush(&mut spans, &mut run, style);
+                lines.push(Line::from(std::mem::take(&mut spans)));
+            }
+            '\x1b' => match chars.next() {
+                // CSI: parameter and intermediate bytes, then a final byte.
+                Some('[') => {
+                    let mut params = String::new();
+                    while let Some(c) = chars.next_if(|c| ('\x20'..='\x3f').contains(c)) {
+                        params.push(c);
+                    }
+                    let end = chars.next_if(|c| ('\x40'..='\x7e').contains(c));
+                    if end == Some('m') && params.chars().all(|c| c.is_ascii_digit() || c == ';' || c == ':') {
+                        flush(&mut spans, &mut run, style);
+                        apply_sgr(&mut style, &params);
+                    }
+                }
+                // OSC, DCS and the other strings run to BEL or ST (ESC \).
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                // Intermediate bytes, then a final byte, e.g. ESC ( B.
+                Some(c) if ('\x20'..='\x2f').contains(&c) => {
+                    while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\t' => run.push(' '),
+            c if c.is_control() => {}
+            c => run.push(c),
+        }
+    }
+    flush(&mut spans, &mut run, style);
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Apply the `;`-separated parameters of an SGR sequence to `style`.
+fn apply_sgr(style: &mut Style, params: &str) {
+    let params: Vec<&str> = params.split(';').collect();
+    let mut i = 0;
+    while i < params.len() {
+        let param = params[i];
+        i += 1;
+        // Sub-parameters after colons, as in 38:2::r:g:b or 4:0.
+        if let Some((code, rest)) = param.split_once(':') {
+            let mut args: Vec<&str> = rest.split(':').collect();
+            if args.first() == Some(&"2") && args.len() > 4 {
+                // Skip the colour space id.
+                args.remove(1);
+            }
+            match code {
+                "38" => style.fg = extended_color(&args).0.or(style.fg),
+                "48" => style.bg = extended_color(&args).0.or(style.bg),
+                "4" if args.first() == Some(&"0") => style.add_modifier.remove(Modifier::UNDERLINED),
+                "4" => style.add_modifier.insert(Modifier::UNDERLINED),
+                _ => {}
+            }
+            continue;
+        }
+        let code: u16 = param.parse().unwrap_or(0);
+        match code {
+            0 => *style = Style::default(),
+            1 => style.add_modifier.insert(Modifier::BOLD),
+            2 => style.add_modifier.insert(Modifier::DIM),
+            3 => style.add_modifier.insert(Modifier::ITALIC),
+            4 => style.add_modifier.insert(Modifier::UNDERLINED),
+            5 => style.add_modifier.insert(Modifier::SLOW_BLINK),
+            6 => style.add_modifier.insert(Modifier::RAPID_BLINK),
+            7 => style.add_modifier.insert(Modifier::REVERSED),
+            8 => style.add_modifier.insert(Modifier::HIDDEN),
+            9 => style.add_modifier.insert(Modifier::CROSSED_OUT),
+            21 | 22 => style.add_modifier.remove(Modifier::BOLD | Modifier::DIM),
+            23 => style.add_modifier.remove(Modifier::ITALIC),
+            24 => style.add_modifier.remove(Modifier::UNDERLINED),
+            25 => style.add_modifier.remove(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+            27 => style.add_modifier.remove(Modifier::REVERSED),
+            28 => style.add_modifier.remove(Modifier::HIDDEN),
+            29 => style.add_modifier.remove(Modifier::CROSSED_OUT),
+            30..=37 => style.fg = Some(basic_color(code - 30)),
+            40..=47 => style.bg = Some(basic_color(code - 40)),
+            90..=97 => style.fg = Some(basic_color(code - 90 + 8)),
+            100..=107 => style.bg = Some(basic_color(code - 100 + 8)),
+            39 => style.fg = None,
+            49 => style.bg = None,
+            38 | 48 | 58 => {
+                let (color, used) = extended_color(&params[i..]);
+                i += used;
+                match (code, color) {
+                    (38, Some(color)) => style.fg = Some(color),
+                    (48, Some(color)) => style.bg = Some(color),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The colour given after 38 or 48, as `5;n` or `2;r;g;b`, and how many
+/// parameters it took.
+fn extended_color(args: &[&str]) -> (Option<Color>, usize) {
+    let num = |i: usize| args.get(i).and_then(|a| a.parse::<u8>().ok());
+    match args.first().copied() {
+        Some("5") => (num(1).map(Color::Indexed), 2),
+        Some("2") => match (num(1), num(2), num(3)) {
+            (Some(r), Some(g), Some(b)) => (Some(Color::Rgb(r, g, b)), 4),
+            _ => (None, 4),
+        },
+        _ => (None, 0),
+    }
+}
+
+/// One of the 16 standard colours: 0-7 normal, 8-15 bright.
+fn basic_color(n: u16) -> Color {
+    const COLORS: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    COLORS[n as usize % COLORS.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_carry_across_lines_and_other_escapes_are_dropped() {
+        // As captured from a pane by `capture-pane -e`.
+        let text = "\x1b[1m\x1b[31mred bold\nstill\x1b[0m\x1b[39m\x1b[49m plain \x1b[38;5;208morange\x1b[39m \
+                    \x1b[38;2;10;200;30mrgb\x1b[39m \x1b[7mrev\n\x1b[0m\x1b]0;title\x07\x1b(Bok\x1b[2J";
+        let lines = parse(text);
+        let spans: Vec<Vec<(&str, Style)>> = lines
+            .iter()
+            .map(|l| l.spans.iter().map(|s| (s.content.as_ref(), s.style)).collect())
+            .collect();
+
+        let red_bold = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        assert_eq!(spans[0], [("red bold", red_bold)]);
+        assert_eq!(
+            spans[1],
+            [
+                ("still", red_bold),
+                (" plain ", Style::default()),
+                ("orange", Style::default().fg(Color::Indexed(208))),
+                (" ", Style::default()),
+                ("rgb", Style::default().fg(Color::Rgb(10, 200, 30))),
+                (" ", Style::default()),
+                ("rev", Style::default().add_modifier(Modifier::REVERSED)),
+            ]
+        );
+        assert_eq!(spans[2], [("ok", Style::default())]);
+    }
+
+    #[test]
+    fn reads_colon_separated_colors_and_resets() {
+        let mut style = Style::default();
+        apply_sgr(&mut style, "38:2::1:2:3;48:5:17;3;4:0;94");
+        assert_eq!(style, Style::default().fg(Color::LightBlue).bg(Color::Indexed(17)).add_modifier(Modifier::ITALIC));
+        apply_sgr(&mut style, "23;39;49");
+        assert_eq!(style, Style::default());
+    }
+}
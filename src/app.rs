@@ -1,11 +1,19 @@
-use std::time::Instant;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use color_eyre::eyre::{Result, eyre};
 use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::text::Line;
 use ratatui::widgets::ListState;
 
-use crate::model::{TmuxPane, TmuxSession, TmuxWindow};
-use crate::template::{self, SessionTemplate};
-use crate::tmux;
+use crate::ansi;
+use crate::export::{self, Export, ExportFormat};
+use crate::fuzzy;
+use crate::import;
+use crate::model::{PaneEntry, TmuxPane, TmuxSession, TmuxWindow};
+use crate::template::{self, Hook, SessionTemplate, TemplateFile};
+use crate::tmux::{self, Socket, TmuxBackend};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Panel {
@@ -56,6 +64,94 @@ pub enum InputMode {
     Confirm,
     Help,
     TemplatePicker,
+    /// Typing a filter for a list panel.
+    Filter,
+    /// The jump-to palette over every pane on every server shown.
+    Jump,
+    /// Searching pane scrollback.
+    Search,
+}
+
+/// Text typed after `/`, narrowing one list panel to the items it fuzzily
+/// matches. Selections still index the full lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListFilter {
+    pub panel: Panel,
+    pub query: String,
+}
+
+/// A pane offered by the jump-to palette.
+#[derive(Debug, Clone)]
+pub struct JumpTarget {
+    pub server: Socket,
+    pub entry: PaneEntry,
+}
+
+impl JumpTarget {
+    /// The line shown and matched against: `session:window.pane`, then the
+    /// window name, command, directory and title.
+    pub fn label(&self) -> String {
+        let e = &self.entry;
+        format!(
+            "{}:{}.{}  {}  {}  {}  {}",
+            e.session, e.window_index, e.pane.index, e.window_name, e.pane.command, e.pane.cwd, e.pane.title
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+/// Scrollback lines the preview loads unless `--history` says otherwise.
+pub const PREVIEW_HISTORY: usize = 1000;
+/// Least time between preview refreshes caused by pane output; output in
+/// between is caught up on the next tick.
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Lines kept either side of a scrollback match, to preview it in context.
+const SEARCH_CONTEXT: usize = 3;
+/// Matches kept per search, so a common word can't flood the list.
+const SEARCH_LIMIT: usize = 500;
+
+/// A line of pane scrollback matching a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub target: JumpTarget,
+    /// Line of the pane's scrollback, oldest first.
+    pub line: usize,
+    /// The matched line with up to `SEARCH_CONTEXT` lines either side.
+    pub context: Vec<String>,
+    /// Position of the matched line in `context`.
+    pub context_line: usize,
+}
+
+impl SearchHit {
+    pub fn text(&self) -> &str {
+        &self.context[self.context_line]
+    }
+}
+
+/// State of the scrollback search overlay.
+#[derive(Debug, Clone, Default)]
+pub struct ScrollbackSearch {
+    pub query: String,
+    /// Query the hits are for; Enter searches again once the query changed.
+    pub searched: Option<String>,
+    /// Session searched, or every pane on every server when `None`.
+    pub session: Option<(Socket, String)>,
+    /// Panes being searched, in the order their scrollback comes in.
+    pub panes: Vec<JumpTarget>,
+    /// How many of `panes` came in, and how many of those had hits.
+    pub captured: usize,
+    pub matched: usize,
+    pub hits: Vec<SearchHit>,
+    pub state: ListState,
+}
+
+impl ScrollbackSearch {
+    /// Whether panes are still to come in.
+    pub fn running(&self) -> bool {
+        self.captured < self.panes.len()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,8 +165,33 @@ pub enum PendingAction {
     KillPane(String),
     SaveTemplate(String),
     OverwriteTemplate(String, SessionTemplate),
+    /// Captured session to save; `n` saves it without its options and
+    /// environment.
+    CaptureSettings(SessionTemplate),
+    /// Template being launched or exported, variables still to ask for (the
+    /// first is being asked now), and the values given so far.
+    TemplateVariable(SessionTemplate, TemplateUse, Vec<String>, BTreeMap<String, String>),
     LaunchTemplate(SessionTemplate),
     DeleteTemplate(String),
+    /// Path of a tmuxinator or tmuxp project file to save as a template.
+    ImportTemplate,
+    /// Template or captured session to export, asking for the format.
+    ChooseExportFormat(SessionTemplate),
+    /// Resolved template to export, asking for the file to write.
+    ExportTemplate(SessionTemplate, ExportFormat),
+    OverwriteExport(PathBuf, Export, ExportFormat),
+    /// Confirmed before applying with pruning, since it kills windows.
+    ApplyTemplate(SessionTemplate, String),
+}
+
+/// What a template's variables are being filled in for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateUse {
+    Launch,
+    Export(ExportFormat),
+    /// Reconcile an existing session with the template, killing what it
+    /// doesn't have when `prune` is set.
+    Apply { session: String, prune: bool },
 }
 
 #[derive(Debug, Clone)]
@@ -80,10 +201,43 @@ pub struct StatusMessage {
     pub created: Instant,
 }
 
+/// Builds a backend for a server socket found by discovery.
+pub type Connector = Box<dyn Fn(&Socket) -> Box<dyn TmuxBackend>>;
+
+/// Starts launching a template as a session on a server, away from the UI
+/// thread; progress and the outcome come back as events.
+pub type Launcher = Box<dyn Fn(Socket, SessionTemplate, String)>;
+
+/// Starts capturing the scrollback of panes, given by server and id, away
+/// from the UI thread for the numbered search; each comes back as an event.
+pub type Searcher = Box<dyn Fn(u64, Vec<(Socket, String)>)>;
+
+/// Starts running a session's hook away from the UI thread, with the status
+/// to show once it is done; the outcome comes back as an event.
+pub type HookRunner = Box<dyn Fn(Hook, String)>;
+
 pub struct App {
+    /// One backend per server shown; the first is the one tmmx was started
+    /// against and is never dropped.
+    pub servers: Vec<Box<dyn TmuxBackend>>,
+    /// Set when showing every server, to pick up servers started later.
+    connector: Option<Connector>,
+    /// Set when template launches run in the background; without it they
+    /// run in place.
+    launcher: Option<Launcher>,
+    /// Set when search captures run in the background; without it they run
+    /// in place.
+    searcher: Option<Searcher>,
+    /// Set when `on_attach` and `on_stop` hooks run in the background;
+    /// without it they run in place.
+    hook_runner: Option<HookRunner>,
+    /// Searches started, numbering them so late captures from an earlier one
+    /// are dropped.
+    searches: u64,
+
     pub running: bool,
     pub should_suspend: bool,
-    pub suspend_target: Option<String>,
+    pub suspend_target: Option<(Socket, String)>,
 
     pub focused: Panel,
     pub mode: InputMode,
@@ -92,10 +246,33 @@ pub struct App {
     pub windows: Vec<TmuxWindow>,
     pub panes: Vec<TmuxPane>,
     pub pane_capture: String,
+    /// `pane_capture` parsed into styled lines, as the preview shows them.
+    preview_lines: Vec<Line<'static>>,
+    /// Pane `pane_capture` was taken from.
+    preview_pane: Option<String>,
+    /// Lines the preview is scrolled back from the end of the capture.
+    pub preview_scroll: usize,
+    /// Capture rows the preview has room for, to page by.
+    pub preview_rows: usize,
+    /// Scrollback lines the preview loads; 0 loads all of it.
+    preview_history: usize,
+    /// When the preview was last captured, and whether output has arrived
+    /// since that a debounced refresh still owes it.
+    preview_refreshed: Instant,
+    preview_stale: bool,
 
     pub session_state: ListState,
     pub window_state: ListState,
     pub pane_state: ListState,
+    pub filter: Option<ListFilter>,
+
+    /// Panes indexed when the jump-to palette opened.
+    pub jump_targets: Vec<JumpTarget>,
+    pub jump_query: String,
+    /// Selected row of `jump_matches`.
+    pub jump_state: ListState,
+
+    pub search: ScrollbackSearch,
 
     pub input_buffer: String,
     pub input_prompt: String,
@@ -104,16 +281,31 @@ pub struct App {
 
     pub status: Option<StatusMessage>,
 
-    pub templates: Vec<SessionTemplate>,
+    pub templates: Vec<TemplateFile>,
     pub template_state: ListState,
+    /// Whether template hooks run when launching, switching to and killing
+    /// sessions.
+    pub hooks: bool,
+    /// Show the launch plan instead of the structure in the picker preview.
+    pub template_show_plan: bool,
+    pub template_preview_scroll: u16,
 
     pub last_refresh: Instant,
     pub refresh_interval_secs: u64,
+    /// Safety-net full refresh while control-mode notifications are flowing,
+    /// for state tmux doesn't notify about (pane commands, activity times).
+    pub idle_refresh_interval_secs: u64,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(tmux: Box<dyn TmuxBackend>) -> Self {
         let mut app = Self {
+            servers: vec![tmux],
+            connector: None,
+            launcher: None,
+            searcher: None,
+            hook_runner: None,
+            searches: 0,
             running: true,
             should_suspend: false,
             suspend_target: None,
@@ -123,9 +315,21 @@ impl App {
             windows: Vec::new(),
             panes: Vec::new(),
             pane_capture: String::new(),
+            preview_lines: Vec::new(),
+            preview_pane: None,
+            preview_scroll: 0,
+            preview_rows: 0,
+            preview_history: PREVIEW_HISTORY,
+            preview_refreshed: Instant::now(),
+            preview_stale: false,
             session_state: ListState::default(),
             window_state: ListState::default(),
             pane_state: ListState::default(),
+            filter: None,
+            jump_targets: Vec::new(),
+            jump_query: String::new(),
+            jump_state: ListState::default(),
+            search: ScrollbackSearch::default(),
             input_buffer: String::new(),
             input_prompt: String::new(),
             pending_action: None,
@@ -133,25 +337,188 @@ impl App {
             status: None,
             templates: Vec::new(),
             template_state: ListState::default(),
+            hooks: true,
+            template_show_plan: false,
+            template_preview_scroll: 0,
             last_refresh: Instant::now(),
             refresh_interval_secs: 2,
+            idle_refresh_interval_secs: 30,
         };
         app.refresh_tmux_state();
         app
     }
 
+    /// Show sessions from every server in the socket directory, not just the
+    /// one tmmx was started against.
+    pub fn enable_discovery(&mut self, connector: Connector) {
+        self.connector = Some(connector);
+        self.refresh_tmux_state();
+    }
+
+    /// Launch templates with `launcher` rather than blocking the UI while
+    /// their panes get ready.
+    pub fn enable_background_launch(&mut self, launcher: Launcher) {
+        self.launcher = Some(launcher);
+    }
+
+    /// Capture panes for scrollback searches with `searcher` rather than
+    /// blocking the UI on each one.
+    pub fn enable_background_search(&mut self, searcher: Searcher) {
+        self.searcher = Some(searcher);
+    }
+
+    /// Run `on_attach` and `on_stop` hooks with `runner` rather than blocking
+    /// the UI until they finish.
+    pub fn enable_background_hooks(&mut self, runner: HookRunner) {
+        self.hook_runner = Some(runner);
+    }
+
+    /// Load `lines` of scrollback into the preview, or all of it with 0.
+    pub fn set_preview_history(&mut self, lines: usize) {
+        self.preview_history = lines;
+        self.refresh_preview();
+    }
+
+    /// Backend for the selected session's server, else the primary server.
+    pub fn tmux(&self) -> &dyn TmuxBackend {
+        let server = self.selected_session().map(|s| s.server);
+        server
+            .and_then(|socket| self.servers.iter().find(|b| b.socket() == socket))
+            .unwrap_or(&self.servers[0])
+            .as_ref()
+    }
+
+    /// Connect to servers that appeared since the last refresh and drop the
+    /// ones whose socket is gone.
+    fn discover_servers(&mut self) {
+        let Some(connect) = &self.connector else {
+            return;
+        };
+        let found = tmux::discover_sockets();
+        let primary = self.servers[0].socket();
+        self.servers
+            .retain(|b| b.socket() == primary || found.contains(&b.socket()));
+        for socket in found {
+            if !self.servers.iter().any(|b| b.socket() == socket) {
+                self.servers.push(connect(&socket));
+            }
+        }
+    }
+
     pub fn refresh_tmux_state(&mut self) {
-        let prev_session = self.selected_session_name();
+        self.discover_servers();
+        let prev_session = self.selected_session_key();
         let prev_window = self.selected_window_index();
         let prev_pane = self.selected_pane_id();
 
-        self.sessions = tmux::list_sessions().unwrap_or_default();
+        self.refresh_sessions(prev_session);
+        self.refresh_windows(prev_window);
+        self.refresh_panes(prev_pane);
+        self.refresh_preview();
+        self.last_refresh = Instant::now();
+    }
+
+    /// Handle `%sessions-changed`: reload the session list, and only cascade
+    /// into windows/panes if the selected session went away.
+    pub fn on_sessions_changed(&mut self) {
+        let prev = self.selected_session_key();
+        self.refresh_sessions(prev.clone());
+        if self.selected_session_key() != prev {
+            self.on_selection_changed(&Panel::Sessions);
+        }
+    }
+
+    /// Handle window add/close/rename. Session window counts change too, so the
+    /// session list is reloaded alongside the selected session's windows.
+    pub fn on_windows_changed(&mut self) {
+        let prev_session = self.selected_session_key();
+        self.refresh_sessions(prev_session.clone());
+        if self.selected_session_key() != prev_session {
+            self.on_selection_changed(&Panel::Sessions);
+            return;
+        }
+
+        let prev_window = self.selected_window().map(|w| w.id);
+        self.refresh_windows(self.selected_window_index());
+        if self.selected_window().map(|w| w.id) != prev_window {
+            self.on_selection_changed(&Panel::Windows);
+        }
+    }
+
+    pub fn on_layout_changed(&mut self, server: &Socket, window_id: &str) {
+        if self.is_selected_server(server)
+            && self.selected_window().is_some_and(|w| w.id == window_id)
+        {
+            self.refresh_windows(self.selected_window_index());
+            self.refresh_panes(self.selected_pane_id());
+            self.refresh_preview();
+        }
+    }
+
+    pub fn on_launch_progress(&mut self, text: String) {
+        self.set_status(text, false);
+    }
+
+    /// Report a finished launch, with the last line its hooks printed.
+    pub fn on_launched(&mut self, session: &str, result: std::result::Result<Vec<String>, String>) {
+        match result {
+            Ok(output) => {
+                let created = format!("Session '{}' created from template", session);
+                match output.last() {
+                    Some(line) => self.set_status(format!("{}; {}", created, line), false),
+                    None => self.set_status(created, false),
+                }
+                self.refresh_tmux_state();
+            }
+            Err(e) => self.set_status(e, true),
+        }
+    }
+
+    /// Status `done` with the outcome of the hook run after it.
+    pub fn on_hook_finished(&mut self, done: String, result: std::result::Result<Option<String>, String>) {
+        match hook_status(done, result.map_err(|e| eyre!(e))) {
+            Ok(msg) => self.set_status(msg, false),
+            Err(e) => self.set_status(e.to_string(), true),
+        }
+    }
+
+    /// Run `hook` now that `done` has happened. In the background the status
+    /// says so until it reports back; in place it has its outcome.
+    fn run_hook(&self, done: String, hook: Result<Option<Hook>>) -> Result<String> {
+        match (hook, &self.hook_runner) {
+            (Ok(Some(hook)), Some(runner)) => {
+                let running = format!("{}; running its {} hook", done, hook.name);
+                runner(hook, done);
+                Ok(running)
+            }
+            (hook, _) => hook_status(done, hook.and_then(|hook| hook.map_or(Ok(None), |h| h.run()))),
+        }
+    }
+
+    pub fn on_pane_output(&mut self, server: &Socket, pane_id: &str) {
+        if self.is_selected_server(server) && self.selected_pane_id().as_deref() == Some(pane_id) {
+            if self.preview_refreshed.elapsed() >= PREVIEW_DEBOUNCE {
+                self.refresh_preview();
+            } else {
+                self.preview_stale = true;
+            }
+        }
+    }
+
+    fn refresh_sessions(&mut self, prev_session: Option<(Socket, String)>) {
+        // Servers are listed in order, so sessions come out grouped by server.
+        // One unreachable server shouldn't hide the others.
+        self.sessions = self
+            .servers
+            .iter()
+            .flat_map(|b| b.list_sessions().unwrap_or_default())
+            .collect();
 
         if !self.sessions.is_empty() {
-            let idx = if let Some(ref name) = prev_session {
+            let idx = if let Some((ref server, ref name)) = prev_session {
                 self.sessions
                     .iter()
-                    .position(|s| &s.name == name)
+                    .position(|s| &s.server == server && &s.name == name)
                     .unwrap_or(0)
             } else {
                 0
@@ -160,16 +527,13 @@ impl App {
         } else {
             self.session_state.select(None);
         }
-
-        self.refresh_windows(prev_window);
-        self.refresh_panes(prev_pane);
-        self.refresh_preview();
-        self.last_refresh = Instant::now();
+        self.keep_selection_visible(&Panel::Sessions);
     }
 
     fn refresh_windows(&mut self, prev_index: Option<u32>) {
         if let Some(session) = self.selected_session() {
-            self.windows = tmux::list_windows(&session.name).unwrap_or_default();
+            self.tmux().follow_session(&session.name);
+            self.windows = self.tmux().list_windows(&session.name).unwrap_or_default();
             if !self.windows.is_empty() {
                 let idx = if let Some(pi) = prev_index {
                     self.windows
@@ -187,11 +551,12 @@ impl App {
             self.windows.clear();
             self.window_state.select(None);
         }
+        self.keep_selection_visible(&Panel::Windows);
     }
 
     fn refresh_panes(&mut self, prev_id: Option<String>) {
         if let (Some(session), Some(window)) = (self.selected_session(), self.selected_window()) {
-            self.panes = tmux::list_panes(&session.name, window.index).unwrap_or_default();
+            self.panes = self.tmux().list_panes(&session.name, window.index).unwrap_or_default();
             if !self.panes.is_empty() {
                 let idx = if let Some(ref pid) = prev_id {
                     self.panes.iter().position(|p| &p.id == pid).unwrap_or(0)
@@ -206,14 +571,51 @@ impl App {
             self.panes.clear();
             self.pane_state.select(None);
         }
+        self.keep_selection_visible(&Panel::Panes);
     }
 
     fn refresh_preview(&mut self) {
-        if let Some(pane) = self.selected_pane() {
-            self.pane_capture = tmux::capture_pane(&pane.id).unwrap_or_default();
-        } else {
+        self.preview_refreshed = Instant::now();
+        self.preview_stale = false;
+        let Some(pane) = self.selected_pane() else {
             self.pane_capture.clear();
+            self.preview_lines.clear();
+            self.preview_pane = None;
+            return;
+        };
+        self.tmux().follow_pane(&pane.id);
+        let depth = (self.preview_history > 0).then_some(self.preview_history);
+        let capture = self.tmux().capture_scrollback(&pane.id, depth, true).unwrap_or_default();
+        let lines = capture_lines(&capture);
+        if self.preview_pane.as_ref() != Some(&pane.id) {
+            self.preview_scroll = 0;
+        } else if self.preview_scroll > 0 {
+            // Keep a view scrolled back on the same lines as output arrives.
+            self.preview_scroll = anchored_scroll(&self.preview_lines, &lines, self.preview_scroll, self.preview_rows);
         }
+        self.pane_capture = capture;
+        self.preview_lines = lines;
+        self.preview_pane = Some(pane.id);
+        self.scroll_preview(0);
+    }
+
+    /// Lines of the capture the preview shows, styled by their escape
+    /// sequences.
+    pub fn preview_lines(&self) -> &[Line<'static>] {
+        &self.preview_lines
+    }
+
+    /// Fit the preview to `rows` of capture, as the terminal size allows.
+    pub fn set_preview_rows(&mut self, rows: usize) {
+        self.preview_rows = rows;
+        self.scroll_preview(0);
+    }
+
+    /// Scroll the preview `lines` further back, or forward when negative,
+    /// staying within the capture.
+    fn scroll_preview(&mut self, lines: isize) {
+        let max = self.preview_lines.len().saturating_sub(self.preview_rows);
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(lines).min(max);
     }
 
     pub fn selected_session(&self) -> Option<TmuxSession> {
@@ -222,8 +624,13 @@ impl App {
             .and_then(|i| self.sessions.get(i).cloned())
     }
 
-    pub fn selected_session_name(&self) -> Option<String> {
-        self.selected_session().map(|s| s.name)
+    fn is_selected_server(&self, server: &Socket) -> bool {
+        self.selected_session().is_some_and(|s| &s.server == server)
+    }
+
+    /// Session names are only unique per server.
+    fn selected_session_key(&self) -> Option<(Socket, String)> {
+        self.selected_session().map(|s| (s.server, s.name))
     }
 
     pub fn selected_window(&self) -> Option<TmuxWindow> {
@@ -255,10 +662,10 @@ impl App {
     }
 
     fn clear_stale_status(&mut self) {
-        if let Some(ref s) = self.status {
-            if s.created.elapsed().as_secs() >= 5 {
-                self.status = None;
-            }
+        if let Some(ref s) = self.status
+            && s.created.elapsed().as_secs() >= 5
+        {
+            self.status = None;
         }
     }
 
@@ -291,6 +698,9 @@ impl App {
             InputMode::Confirm => self.handle_confirm_key(key),
             InputMode::TextInput => self.handle_text_input_key(key),
             InputMode::TemplatePicker => self.handle_template_picker_key(key),
+            InputMode::Filter => self.handle_filter_key(key),
+            InputMode::Jump => self.handle_jump_key(key),
+            InputMode::Search => self.handle_search_key(key),
             InputMode::Normal => self.handle_normal_key(key),
         }
     }
@@ -305,13 +715,19 @@ impl App {
     fn handle_confirm_key(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Char('y') | KeyCode::Enter => {
-                if let Some(action) = self.pending_action.take() {
+                // Reset first: the action may open a follow-up prompt.
+                let action = self.pending_action.take();
+                self.cancel_input();
+                if let Some(action) = action {
                     self.execute_action(action);
                 }
-                self.cancel_input();
             }
             KeyCode::Char('n') | KeyCode::Esc => {
+                let action = self.pending_action.take();
                 self.cancel_input();
+                if let (KeyCode::Char('n'), Some(PendingAction::CaptureSettings(t))) = (key.code, action) {
+                    self.save_captured_template(t.without_settings());
+                }
             }
             _ => {}
         }
@@ -320,11 +736,13 @@ impl App {
     fn handle_text_input_key(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Enter => {
+                // Reset first: the action may open a follow-up prompt.
                 let value = self.input_buffer.clone();
-                if let Some(action) = self.pending_action.take() {
+                let action = self.pending_action.take();
+                self.cancel_input();
+                if let Some(action) = action {
                     self.execute_text_action(action, &value);
                 }
-                self.cancel_input();
             }
             KeyCode::Esc => self.cancel_input(),
             KeyCode::Backspace => {
@@ -353,6 +771,23 @@ impl App {
                 self.set_status("Refreshed".to_string(), false);
                 return;
             }
+            KeyCode::Char('/') if self.focused != Panel::Preview => {
+                self.start_filter();
+                return;
+            }
+            KeyCode::Char('f') => {
+                self.open_jump();
+                return;
+            }
+            KeyCode::Char('F') => {
+                self.search = ScrollbackSearch::default();
+                self.mode = InputMode::Search;
+                return;
+            }
+            KeyCode::Esc if self.filter.is_some() => {
+                self.clear_filter();
+                return;
+            }
             KeyCode::Char('1') => {
                 self.focused = Panel::Sessions;
                 return;
@@ -435,8 +870,16 @@ impl App {
                     );
                 }
             }
+            KeyCode::Char('x') => {
+                if let Some(session) = self.selected_session() {
+                    match template::capture_session_as_template(self.tmux(), &session.name, false) {
+                        Ok(t) => self.start_export(t),
+                        Err(e) => self.set_status(e.to_string(), true),
+                    }
+                }
+            }
             KeyCode::Char('t') => {
-                self.templates = template::load_all_templates();
+                self.templates = template::load_template_files();
                 if !self.templates.is_empty() {
                     self.template_state.select(Some(0));
                 } else {
@@ -535,7 +978,14 @@ impl App {
     }
 
     fn handle_preview_key(&mut self, key: KeyEvent) {
+        let page = self.preview_rows.max(1) as isize;
         match key.code {
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_preview(1),
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_preview(-1),
+            KeyCode::PageUp => self.scroll_preview(page),
+            KeyCode::PageDown => self.scroll_preview(-page),
+            KeyCode::Char('g') => self.scroll_preview(isize::MAX),
+            KeyCode::Char('G') => self.preview_scroll = 0,
             KeyCode::Char('l') | KeyCode::Right => {
                 self.focused = self.focused.next();
             }
@@ -546,28 +996,356 @@ impl App {
         }
     }
 
-    fn move_selection_down(&mut self, panel: &Panel) {
-        let (state, len) = self.state_and_len(panel);
-        if len == 0 {
+    /// Index every pane on every server shown and open the palette on it.
+    fn open_jump(&mut self) {
+        self.jump_targets = self
+            .servers
+            .iter()
+            .flat_map(|b| {
+                let server = b.socket();
+                let entries = b.list_all_panes().unwrap_or_default();
+                entries.into_iter().map(move |entry| JumpTarget {
+                    server: server.clone(),
+                    entry,
+                })
+            })
+            .collect();
+        self.jump_query.clear();
+        self.jump_state.select((!self.jump_targets.is_empty()).then_some(0));
+        self.mode = InputMode::Jump;
+    }
+
+    /// Palette entries the query matches, as indices into `jump_targets`
+    /// with the matched characters of each label. Unbroken matches come
+    /// first, otherwise the server's order is kept.
+    pub fn jump_matches(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut matches: Vec<_> = self
+            .jump_targets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| fuzzy::fuzzy_match(&self.jump_query, &t.label()).map(|matched| (i, matched)))
+            .collect();
+        matches.sort_by_key(|(_, matched)| matched.windows(2).any(|pair| pair[1] != pair[0] + 1));
+        matches
+    }
+
+    fn handle_jump_key(&mut self, key: KeyEvent) {
+        let len = self.jump_matches().len();
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = InputMode::Normal;
+                self.jump_targets.clear();
+            }
+            KeyCode::Enter => {
+                let target = self
+                    .jump_state
+                    .selected()
+                    .and_then(|row| self.jump_matches().get(row).map(|(i, _)| self.jump_targets[*i].clone()));
+                self.mode = InputMode::Normal;
+                self.jump_targets.clear();
+                if let Some(target) = target
+                    && self.select_jump_target(&target)
+                {
+                    self.switch_to_selected_pane();
+                }
+            }
+            KeyCode::Down if len > 0 => {
+                let row = self.jump_state.selected().map_or(0, |row| (row + 1).min(len - 1));
+                self.jump_state.select(Some(row));
+            }
+            KeyCode::Up if len > 0 => {
+                let row = self.jump_state.selected().map_or(0, |row| row.saturating_sub(1));
+                self.jump_state.select(Some(row));
+            }
+            KeyCode::Backspace => {
+                self.jump_query.pop();
+                self.jump_state.select((!self.jump_matches().is_empty()).then_some(0));
+            }
+            KeyCode::Char(c) => {
+                self.jump_query.push(c);
+                self.jump_state.select((!self.jump_matches().is_empty()).then_some(0));
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let len = self.search.hits.len();
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = InputMode::Normal;
+                self.search = ScrollbackSearch::default();
+            }
+            // Switch between every server and the selected session.
+            KeyCode::Tab => {
+                self.search.session = match self.search.session {
+                    Some(_) => None,
+                    None => self.selected_session_key(),
+                };
+                if self.search.searched.is_some() {
+                    self.run_search();
+                }
+            }
+            KeyCode::Enter if self.search.query.is_empty() => {}
+            KeyCode::Enter if self.search.searched.as_ref() != Some(&self.search.query) => self.run_search(),
+            KeyCode::Enter => self.jump_to_search_hit(),
+            KeyCode::Down if len > 0 => {
+                let row = self.search.state.selected().map_or(0, |row| (row + 1).min(len - 1));
+                self.search.state.select(Some(row));
+            }
+            KeyCode::Up if len > 0 => {
+                let row = self.search.state.selected().map_or(0, |row| row.saturating_sub(1));
+                self.search.state.select(Some(row));
+            }
+            KeyCode::Backspace => {
+                self.search.query.pop();
+            }
+            KeyCode::Char(c) => self.search.query.push(c),
+            _ => {}
+        }
+    }
+
+    /// Look for the query, ignoring case, in the whole scrollback of every
+    /// pane in scope. Hits come in pane by pane as their scrollback does.
+    fn run_search(&mut self) {
+        let scope = &self.search.session;
+        let mut panes = Vec::new();
+        for backend in &self.servers {
+            let server = backend.socket();
+            if scope.as_ref().is_some_and(|(s, _)| *s != server) {
+                continue;
+            }
+            for entry in backend.list_all_panes().unwrap_or_default() {
+                if scope.as_ref().is_none_or(|(_, name)| *name == entry.session) {
+                    panes.push(JumpTarget {
+                        server: server.clone(),
+                        entry,
+                    });
+                }
+            }
+        }
+
+        self.searches += 1;
+        self.search.searched = Some(self.search.query.clone());
+        self.search.panes = panes;
+        self.search.captured = 0;
+        self.search.matched = 0;
+        self.search.hits.clear();
+        self.search.state.select(None);
+        self.report_search();
+        match &self.searcher {
+            Some(search) => {
+                let panes = self.search.panes.iter().map(|t| (t.server.clone(), t.entry.pane.id.clone()));
+                search(self.searches, panes.collect());
+            }
+            None => {
+                for (i, target) in self.search.panes.clone().into_iter().enumerate() {
+                    let backend = self.servers.iter().find(|b| b.socket() == target.server);
+                    let text = backend.and_then(|b| b.capture_scrollback(&target.entry.pane.id, None, false).ok());
+                    self.on_scrollback(self.searches, i, text);
+                }
+            }
+        }
+    }
+
+    /// Add the hits in the scrollback of the `pane`th pane of search number
+    /// `search`, unless that search was closed or replaced. `None` if the
+    /// pane couldn't be captured.
+    pub fn on_scrollback(&mut self, search: u64, pane: usize, text: Option<String>) {
+        if search != self.searches {
             return;
         }
-        let i = state.selected().unwrap_or(0);
-        let next = if i >= len - 1 { len - 1 } else { i + 1 };
-        state.select(Some(next));
-        self.on_selection_changed(panel);
+        let (Some(target), Some(query)) = (self.search.panes.get(pane).cloned(), self.search.searched.clone()) else {
+            return;
+        };
+        self.search.captured += 1;
+        let query = query.to_lowercase();
+        let lines: Vec<&str> = text.as_deref().unwrap_or_default().lines().collect();
+        let hits = &mut self.search.hits;
+        let before = hits.len();
+        for (i, line) in lines.iter().enumerate() {
+            if hits.len() == SEARCH_LIMIT {
+                break;
+            }
+            if !line.to_lowercase().contains(&query) {
+                continue;
+            }
+            let start = i.saturating_sub(SEARCH_CONTEXT);
+            let end = (i + SEARCH_CONTEXT + 1).min(lines.len());
+            hits.push(SearchHit {
+                target: target.clone(),
+                line: i,
+                context: lines[start..end].iter().map(|l| l.to_string()).collect(),
+                context_line: i - start,
+            });
+        }
+        if hits.len() > before {
+            self.search.matched += 1;
+        }
+        if self.search.state.selected().is_none() && !self.search.hits.is_empty() {
+            self.search.state.select(Some(0));
+        }
+        self.report_search();
+    }
+
+    fn report_search(&mut self) {
+        let search = &self.search;
+        let query = search.searched.clone().unwrap_or_default();
+        let status = match search.hits.len() {
+            n if search.running() => {
+                format!("Searching {}/{} panes: {} match(es)", search.captured, search.panes.len(), n)
+            }
+            0 => format!("No matches for '{}'", query),
+            SEARCH_LIMIT => format!("First {} matches, in {} pane(s)", SEARCH_LIMIT, search.matched),
+            n => format!("{} match(es) in {} pane(s)", n, search.matched),
+        };
+        let failed = !search.running() && search.hits.is_empty();
+        self.set_status(status, failed);
+    }
+
+    /// Switch to the selected hit's pane, in copy mode on the matched line.
+    fn jump_to_search_hit(&mut self) {
+        let Some(hit) = self.search.state.selected().and_then(|row| self.search.hits.get(row)).cloned() else {
+            return;
+        };
+        self.mode = InputMode::Normal;
+        self.search = ScrollbackSearch::default();
+        if !self.select_jump_target(&hit.target) {
+            return;
+        }
+        if let Err(e) = self.tmux().copy_mode_at(&hit.target.entry.pane.id, hit.line) {
+            self.set_status(e.to_string(), true);
+            return;
+        }
+        self.switch_to_selected_pane();
+    }
+
+    /// Select the target's session, window and pane in the lists, dropping
+    /// any filter that would hide them. Returns false if the pane is gone.
+    fn select_jump_target(&mut self, target: &JumpTarget) -> bool {
+        let entry = &target.entry;
+        self.filter = None;
+        self.refresh_sessions(Some((target.server.clone(), entry.session.clone())));
+        self.refresh_windows(Some(entry.window_index));
+        self.refresh_panes(Some(entry.pane.id.clone()));
+        self.refresh_preview();
+        let found = self.selected_session_key() == Some((target.server.clone(), entry.session.clone()))
+            && self.selected_pane_id().as_deref() == Some(entry.pane.id.as_str());
+        if found {
+            self.focused = Panel::Panes;
+        } else {
+            let name = format!("{}:{}.{}", entry.session, entry.window_index, entry.pane.index);
+            self.set_status(format!("Pane {} no longer exists", name), true);
+        }
+        found
+    }
+
+    fn move_selection_down(&mut self, panel: &Panel) {
+        self.move_selection(panel, |pos, len| (pos + 1).min(len - 1));
     }
 
     fn move_selection_up(&mut self, panel: &Panel) {
-        let (state, len) = self.state_and_len(panel);
-        if len == 0 {
+        self.move_selection(panel, |pos, _| pos.saturating_sub(1));
+    }
+
+    /// Move among the items the filter leaves: `step` maps the selection's
+    /// position among them, and their count, to the new position.
+    fn move_selection(&mut self, panel: &Panel, step: impl Fn(usize, usize) -> usize) {
+        let visible: Vec<usize> = self.visible_items(panel).into_iter().map(|(i, _)| i).collect();
+        if visible.is_empty() {
             return;
         }
-        let i = state.selected().unwrap_or(0);
-        let next = i.saturating_sub(1);
-        state.select(Some(next));
+        let (state, _) = self.state_and_len(panel);
+        let pos = state.selected().and_then(|s| visible.iter().position(|&v| v == s)).unwrap_or(0);
+        state.select(Some(visible[step(pos, visible.len())]));
         self.on_selection_changed(panel);
     }
 
+    /// The text a filter is matched against, for each item of `panel`.
+    fn filter_labels(&self, panel: &Panel) -> Vec<&str> {
+        match panel {
+            Panel::Sessions => self.sessions.iter().map(|s| s.name.as_str()).collect(),
+            Panel::Windows => self.windows.iter().map(|w| w.name.as_str()).collect(),
+            Panel::Panes => self.panes.iter().map(|p| p.command.as_str()).collect(),
+            Panel::Preview => Vec::new(),
+        }
+    }
+
+    /// The items of `panel` its filter leaves, as indices into the full list
+    /// with the matched characters of each. Unfiltered, that is every item.
+    pub fn visible_items(&self, panel: &Panel) -> Vec<(usize, Vec<usize>)> {
+        let query = self.filter.as_ref().filter(|f| f.panel == *panel).map_or("", |f| f.query.as_str());
+        self.filter_labels(panel)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, label)| fuzzy::fuzzy_match(query, label).map(|matched| (i, matched)))
+            .collect()
+    }
+
+    /// Move a selection the filter hides to the first item it leaves, or to
+    /// nothing when no item matches. Returns whether the selection changed.
+    fn keep_selection_visible(&mut self, panel: &Panel) -> bool {
+        let visible: Vec<usize> = self.visible_items(panel).into_iter().map(|(i, _)| i).collect();
+        let (state, _) = self.state_and_len(panel);
+        let selected = state.selected();
+        if selected.is_some_and(|s| visible.contains(&s)) {
+            return false;
+        }
+        state.select(visible.first().copied());
+        state.selected() != selected
+    }
+
+    /// Filter the focused list, picking up its query if it already has one.
+    fn start_filter(&mut self) {
+        let panel = self.focused;
+        if self.filter.as_ref().is_none_or(|f| f.panel != panel) {
+            self.clear_filter();
+            self.filter = Some(ListFilter {
+                panel,
+                query: String::new(),
+            });
+        }
+        self.mode = InputMode::Filter;
+    }
+
+    fn clear_filter(&mut self) {
+        self.mode = InputMode::Normal;
+        if let Some(filter) = self.filter.take()
+            && self.keep_selection_visible(&filter.panel)
+        {
+            self.on_selection_changed(&filter.panel);
+        }
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        let Some(filter) = &mut self.filter else {
+            self.mode = InputMode::Normal;
+            return;
+        };
+        let panel = filter.panel;
+        match key.code {
+            KeyCode::Esc => return self.clear_filter(),
+            // Keep the list narrowed, so the panel's keys act on what is left.
+            KeyCode::Enter => {
+                if filter.query.is_empty() {
+                    self.filter = None;
+                }
+                self.mode = InputMode::Normal;
+                return;
+            }
+            KeyCode::Down => return self.move_selection_down(&panel),
+            KeyCode::Up => return self.move_selection_up(&panel),
+            KeyCode::Backspace => {
+                filter.query.pop();
+            }
+            KeyCode::Char(c) => filter.query.push(c),
+            _ => return,
+        }
+        if self.keep_selection_visible(&panel) {
+            self.on_selection_changed(&panel);
+        }
+    }
+
     fn state_and_len(&mut self, panel: &Panel) -> (&mut ListState, usize) {
         match panel {
             Panel::Sessions => (&mut self.session_state, self.sessions.len()),
@@ -598,22 +1376,39 @@ impl App {
     fn execute_action(&mut self, action: PendingAction) {
         let result = match action {
             PendingAction::KillSession(ref name) => {
-                tmux::kill_session(name).map(|_| format!("Session '{}' killed", name))
+                // The hook is recorded on the session, so it is looked up first.
+                let hook = if self.hooks { template::session_hook(self.tmux(), name, "on_stop") } else { Ok(None) };
+                self.tmux()
+                    .kill_session(name)
+                    .and_then(|_| self.run_hook(format!("Session '{}' killed", name), hook))
             }
             PendingAction::KillWindow(ref session, index) => {
-                tmux::kill_window(session, index).map(|_| format!("Window {}:{} killed", session, index))
+                self.tmux().kill_window(session, index).map(|_| format!("Window {}:{} killed", session, index))
             }
             PendingAction::KillPane(ref id) => {
-                tmux::kill_pane(id).map(|_| format!("Pane '{}' killed", id))
+                self.tmux().kill_pane(id).map(|_| format!("Pane '{}' killed", id))
             }
             PendingAction::DeleteTemplate(ref name) => {
                 template::delete_template(name).map(|_| format!("Template '{}' deleted", name))
             }
+            PendingAction::CaptureSettings(t) => {
+                return self.save_captured_template(t);
+            }
             PendingAction::OverwriteTemplate(ref name, ref t) => {
                 let mut t = t.clone();
                 t.template.name = name.clone();
                 template::save_template(&t).map(|_| format!("Template '{}' saved", name))
             }
+            PendingAction::ApplyTemplate(t, session) => {
+                let unset = template::unset_variables(&t);
+                let purpose = TemplateUse::Apply { session, prune: true };
+                return self.prompt_template_variables(t, purpose, unset, BTreeMap::new());
+            }
+            PendingAction::OverwriteExport(ref path, ref exported, format) => {
+                export::write_export(path, &exported.content, format).map(|_| {
+                    with_warnings(format!("Exported to {}", path.display()), &exported.warnings)
+                })
+            }
             _ => return,
         };
         match result {
@@ -631,19 +1426,19 @@ impl App {
                 if value.is_empty() {
                     return;
                 }
-                tmux::new_session(value).map(|_| format!("Session '{}' created", value))
+                self.tmux().new_session(value).map(|_| format!("Session '{}' created", value))
             }
             PendingAction::RenameSession(ref old) => {
                 if value.is_empty() {
                     return;
                 }
-                tmux::rename_session(old, value)
+                self.tmux().rename_session(old, value)
                     .map(|_| format!("Session renamed to '{}'", value))
             }
             PendingAction::CreateWindow => {
                 if let Some(session) = self.selected_session() {
                     let name = if value.is_empty() { None } else { Some(value) };
-                    tmux::new_window(&session.name, name).map(|_| "Window created".to_string())
+                    self.tmux().new_window(&session.name, name).map(|_| "Window created".to_string())
                 } else {
                     return;
                 }
@@ -652,32 +1447,85 @@ impl App {
                 if value.is_empty() {
                     return;
                 }
-                tmux::rename_window(session, index, value)
+                self.tmux().rename_window(session, index, value)
                     .map(|_| format!("Window renamed to '{}'", value))
             }
             PendingAction::SaveTemplate(ref session_name) => {
                 if value.is_empty() {
                     return;
                 }
-                // Check if template exists — if so, confirm overwrite
-                if template::template_exists(value) {
-                    match template::capture_session_as_template(session_name) {
-                        Ok(mut t) => {
-                            t.template.name = value.to_string();
+                match template::capture_session_as_template(self.tmux(), session_name, true) {
+                    Ok(mut t) => {
+                        t.template.name = value.to_string();
+                        match t.settings_count() {
+                            0 => self.save_captured_template(t),
+                            count => self.start_confirm(
+                                &format!(
+                                    "Also save the {} option(s) and environment variable(s) set on '{}'? (y/n)",
+                                    count, session_name
+                                ),
+                                PendingAction::CaptureSettings(t),
+                            ),
+                        }
+                    }
+                    Err(e) => self.set_status(e.to_string(), true),
+                }
+                return;
+            }
+            PendingAction::TemplateVariable(t, purpose, mut remaining, mut values) => {
+                values.insert(remaining.remove(0), value.to_string());
+                return self.prompt_template_variables(t, purpose, remaining, values);
+            }
+            PendingAction::ChooseExportFormat(t) => {
+                match value.parse::<ExportFormat>() {
+                    Ok(format) => {
+                        let unset = template::unset_variables(&t);
+                        self.prompt_template_variables(t, TemplateUse::Export(format), unset, BTreeMap::new());
+                    }
+                    Err(e) => self.set_status(e.to_string(), true),
+                }
+                return;
+            }
+            PendingAction::ExportTemplate(ref t, format) => {
+                if value.is_empty() {
+                    return;
+                }
+                let path = PathBuf::from(import::expand_home(value));
+                match export::export(t, format) {
+                    Ok(exported) if path.exists() => {
+                        self.start_confirm(
+                            &format!("{} exists. Overwrite? (y/n)", path.display()),
+                            PendingAction::OverwriteExport(path, exported, format),
+                        );
+                        return;
+                    }
+                    Ok(exported) => export::write_export(&path, &exported.content, format).map(|_| {
+                        let msg = format!("Exported '{}' to {}", t.template.name, path.display());
+                        with_warnings(msg, &exported.warnings)
+                    }),
+                    Err(e) => Err(e),
+                }
+            }
+            PendingAction::ImportTemplate => {
+                if value.is_empty() {
+                    return;
+                }
+                let path = import::expand_home(value);
+                match import::import_file(std::path::Path::new(&path)) {
+                    Ok(imported) => {
+                        let name = imported.template.template.name.clone();
+                        let msg = with_warnings(format!("Template '{}' imported", name), &imported.warnings);
+                        if template::template_exists(&name) {
+                            if !imported.warnings.is_empty() {
+                                self.set_status(msg, false);
+                            }
                             self.start_confirm(
-                                &format!("Template '{}' exists. Overwrite? (y/n)", value),
-                                PendingAction::OverwriteTemplate(value.to_string(), t),
+                                &format!("Template '{}' exists. Overwrite? (y/n)", name),
+                                PendingAction::OverwriteTemplate(name, imported.template),
                             );
                             return;
                         }
-                        Err(e) => return self.set_status(e.to_string(), true),
-                    }
-                }
-                match template::capture_session_as_template(session_name) {
-                    Ok(mut t) => {
-                        t.template.name = value.to_string();
-                        template::save_template(&t)
-                            .map(|_| format!("Template '{}' saved", value))
+                        template::save_template(&imported.template).map(|_| msg)
                     }
                     Err(e) => Err(e),
                 }
@@ -686,8 +1534,18 @@ impl App {
                 if value.is_empty() {
                     return;
                 }
-                template::launch_template(t, value)
-                    .map(|_| format!("Session '{}' created from template", value))
+                let t = if self.hooks { t.clone() } else { t.clone().without_hooks() };
+                match &self.launcher {
+                    Some(launch) => {
+                        launch(self.tmux().socket(), t, value.to_string());
+                        self.set_status(format!("Launching session '{}' from template", value), false);
+                    }
+                    None => {
+                        let result = template::launch_template(self.tmux(), &t, value);
+                        self.on_launched(value, result.map_err(|e| e.to_string()));
+                    }
+                }
+                return;
             }
             _ => return,
         };
@@ -702,34 +1560,19 @@ impl App {
 
     fn switch_to_selected_session(&mut self) {
         if let Some(session) = self.selected_session() {
-            if tmux::is_inside_tmux() {
-                match tmux::switch_client(&session.name) {
-                    Ok(_) => self.set_status(format!("Switched to '{}'", session.name), false),
-                    Err(e) => self.set_status(e.to_string(), true),
-                }
-            } else {
-                self.should_suspend = true;
-                self.suspend_target = Some(session.name.clone());
-            }
+            self.switch_client_to(&session.server, session.name);
         }
     }
 
     fn switch_to_selected_window(&mut self) {
         if let (Some(session), Some(window)) = (self.selected_session(), self.selected_window()) {
-            let target = format!("{}:{}", session.name, window.index);
-            if tmux::is_inside_tmux() {
-                if let Err(e) = tmux::select_window(&session.name, window.index) {
-                    self.set_status(e.to_string(), true);
-                    return;
-                }
-                match tmux::switch_client(&target) {
-                    Ok(_) => self.set_status(format!("Switched to {}", target), false),
-                    Err(e) => self.set_status(e.to_string(), true),
-                }
-            } else {
-                self.should_suspend = true;
-                self.suspend_target = Some(target);
+            if tmux::is_inside_tmux()
+                && let Err(e) = self.tmux().select_window(&session.name, window.index)
+            {
+                self.set_status(e.to_string(), true);
+                return;
             }
+            self.switch_client_to(&session.server, format!("{}:{}", session.name, window.index));
         }
     }
 
@@ -737,24 +1580,49 @@ impl App {
         if let (Some(session), Some(window), Some(pane)) =
             (self.selected_session(), self.selected_window(), self.selected_pane())
         {
-            let target = format!("{}:{}.{}", session.name, window.index, pane.index);
             if tmux::is_inside_tmux() {
-                if let Err(e) = tmux::select_window(&session.name, window.index) {
+                if let Err(e) = self.tmux().select_window(&session.name, window.index) {
                     self.set_status(e.to_string(), true);
                     return;
                 }
-                if let Err(e) = tmux::select_pane(&pane.id) {
+                if let Err(e) = self.tmux().select_pane(&pane.id) {
                     self.set_status(e.to_string(), true);
                     return;
                 }
-                match tmux::switch_client(&format!("{}:{}", session.name, window.index)) {
-                    Ok(_) => self.set_status(format!("Switched to {}", target), false),
-                    Err(e) => self.set_status(e.to_string(), true),
+            }
+            let target = format!("{}:{}.{}", session.name, window.index, pane.index);
+            self.switch_client_to(&session.server, target);
+        }
+    }
+
+    /// Move the user's terminal to `target`: `switch-client` within the server
+    /// tmmx runs in, hand the client over when the target is on another server,
+    /// or leave the TUI and attach when not inside tmux at all. The session's
+    /// `on_attach` hook runs once the switch is made; before attaching from
+    /// outside tmux, so a failing hook can still be shown and the attach
+    /// called off.
+    fn switch_client_to(&mut self, server: &Socket, target: String) {
+        let session = target.split(':').next().unwrap_or_default();
+        let hook = if self.hooks { template::session_hook(self.tmux(), session, "on_attach") } else { Ok(None) };
+        let result = if self.tmux().contains_current_client() {
+            self.tmux().switch_client(&target)
+        } else if tmux::is_inside_tmux() {
+            tmux::switch_server(server, &target)
+        } else {
+            // The attach takes over the terminal, so its hook has to be done
+            // first.
+            match hook.and_then(|hook| hook.map_or(Ok(None), |h| h.run())) {
+                Ok(_) => {
+                    self.should_suspend = true;
+                    self.suspend_target = Some((server.clone(), target));
                 }
-            } else {
-                self.should_suspend = true;
-                self.suspend_target = Some(target);
+                Err(e) => self.set_status(format!("Not attaching to {}: {}", target, e), true),
             }
+            return;
+        };
+        match result.and_then(|_| self.run_hook(format!("Switched to {}", target), hook)) {
+            Ok(msg) => self.set_status(msg, false),
+            Err(e) => self.set_status(e.to_string(), true),
         }
     }
 
@@ -762,7 +1630,7 @@ impl App {
         if let (Some(session), Some(window), Some(pane)) =
             (self.selected_session(), self.selected_window(), self.selected_pane())
         {
-            match tmux::split_window_vertical(&session.name, window.index, &pane.id) {
+            match self.tmux().split_window_vertical(&session.name, window.index, &pane.id) {
                 Ok(_) => {
                     self.set_status("Pane split vertically".to_string(), false);
                     self.refresh_tmux_state();
@@ -776,7 +1644,7 @@ impl App {
         if let (Some(session), Some(window), Some(pane)) =
             (self.selected_session(), self.selected_window(), self.selected_pane())
         {
-            match tmux::split_window_horizontal(&session.name, window.index, &pane.id) {
+            match self.tmux().split_window_horizontal(&session.name, window.index, &pane.id) {
                 Ok(_) => {
                     self.set_status("Pane split horizontally".to_string(), false);
                     self.refresh_tmux_state();
@@ -788,7 +1656,7 @@ impl App {
 
     fn toggle_zoom_pane(&mut self) {
         if let Some(pane) = self.selected_pane() {
-            match tmux::resize_pane_zoom(&pane.id) {
+            match self.tmux().resize_pane_zoom(&pane.id) {
                 Ok(_) => self.set_status("Pane zoom toggled".to_string(), false),
                 Err(e) => self.set_status(e.to_string(), true),
             }
@@ -797,7 +1665,7 @@ impl App {
 
     fn break_pane_to_window(&mut self) {
         if let Some(pane) = self.selected_pane() {
-            match tmux::break_pane(&pane.id) {
+            match self.tmux().break_pane(&pane.id) {
                 Ok(_) => {
                     self.set_status("Pane broken to new window".to_string(), false);
                     self.refresh_tmux_state();
@@ -812,62 +1680,680 @@ impl App {
             KeyCode::Esc => {
                 self.mode = InputMode::Normal;
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if !self.templates.is_empty() {
-                    let i = self.template_state.selected().unwrap_or(0);
-                    let next = if i >= self.templates.len() - 1 {
-                        self.templates.len() - 1
-                    } else {
-                        i + 1
-                    };
-                    self.template_state.select(Some(next));
-                }
+            KeyCode::Char('j') | KeyCode::Down if !self.templates.is_empty() => {
+                let i = self.template_state.selected().unwrap_or(0);
+                let next = if i >= self.templates.len() - 1 {
+                    self.templates.len() - 1
+                } else {
+                    i + 1
+                };
+                self.template_state.select(Some(next));
+                self.template_preview_scroll = 0;
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if !self.templates.is_empty() {
-                    let i = self.template_state.selected().unwrap_or(0);
-                    self.template_state.select(Some(i.saturating_sub(1)));
-                }
+            KeyCode::Char('k') | KeyCode::Up if !self.templates.is_empty() => {
+                let i = self.template_state.selected().unwrap_or(0);
+                self.template_state.select(Some(i.saturating_sub(1)));
+                self.template_preview_scroll = 0;
+            }
+            KeyCode::Char('p') => {
+                self.template_show_plan = !self.template_show_plan;
+                self.template_preview_scroll = 0;
+            }
+            KeyCode::Char('J') | KeyCode::PageDown => {
+                let step = if key.code == KeyCode::PageDown { 10 } else { 1 };
+                self.template_preview_scroll = self.template_preview_scroll.saturating_add(step);
+            }
+            KeyCode::Char('K') | KeyCode::PageUp => {
+                let step = if key.code == KeyCode::PageUp { 10 } else { 1 };
+                self.template_preview_scroll = self.template_preview_scroll.saturating_sub(step);
             }
             KeyCode::Enter => {
-                if let Some(idx) = self.template_state.selected() {
-                    if let Some(t) = self.templates.get(idx).cloned() {
-                        self.mode = InputMode::Normal;
-                        let name = t.template.name.clone();
-                        self.start_text_input(
-                            "Session name: ",
-                            &name,
-                            PendingAction::LaunchTemplate(t),
-                        );
-                    }
+                if let Some(t) = self.selected_template().cloned() {
+                    self.mode = InputMode::Normal;
+                    let unset = template::unset_variables(&t);
+                    self.prompt_template_variables(t, TemplateUse::Launch, unset, BTreeMap::new());
+                } else if let Some(file) = self.selected_template_file() {
+                    let message = file.report(&file.problems[0]);
+                    self.set_status(message, true);
                 }
             }
             KeyCode::Char('d') => {
-                if let Some(idx) = self.template_state.selected() {
-                    if let Some(t) = self.templates.get(idx) {
-                        let name = t.template.name.clone();
-                        self.mode = InputMode::Normal;
-                        self.start_confirm(
-                            &format!("Delete template '{}'? (y/n)", name),
-                            PendingAction::DeleteTemplate(name),
+                if let Some(file) = self.selected_template_file()
+                    && file.project.is_some()
+                {
+                    // It belongs to the repository, not to tmmx.
+                    let message = format!(
+                        "'{}' is a project template; remove {} instead",
+                        file.name(),
+                        file.path.display()
+                    );
+                    self.set_status(message, true);
+                } else if let Some(file) = self.selected_template_file() {
+                    let name = file.name();
+                    self.mode = InputMode::Normal;
+                    self.start_confirm(
+                        &format!("Delete template '{}'? (y/n)", name),
+                        PendingAction::DeleteTemplate(name),
+                    );
+                }
+            }
+            KeyCode::Char('i') => {
+                self.mode = InputMode::Normal;
+                self.start_text_input("Import tmuxinator/tmuxp file: ", "", PendingAction::ImportTemplate);
+            }
+            KeyCode::Char('x') => {
+                if let Some(t) = self.selected_template().cloned() {
+                    self.mode = InputMode::Normal;
+                    self.start_export(t);
+                }
+            }
+            KeyCode::Char(c @ ('a' | 'A')) => {
+                let (Some(t), Some(session)) = (self.selected_template().cloned(), self.selected_session()) else {
+                    return;
+                };
+                self.mode = InputMode::Normal;
+                if c == 'A' {
+                    self.start_confirm(
+                        &format!(
+                            "Apply '{}' to '{}' and kill windows and panes it doesn't have? (y/n)",
+                            t.template.name, session.name
+                        ),
+                        PendingAction::ApplyTemplate(t, session.name),
+                    );
+                } else {
+                    let unset = template::unset_variables(&t);
+                    let purpose = TemplateUse::Apply {
+                        session: session.name,
+                        prune: false,
+                    };
+                    self.prompt_template_variables(t, purpose, unset, BTreeMap::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ask for the next unset template variable. Once every variable has a
+    /// value, ask for the session name to launch as, or the file to export to.
+    fn prompt_template_variables(
+        &mut self,
+        t: SessionTemplate,
+        purpose: TemplateUse,
+        remaining: Vec<String>,
+        values: BTreeMap<String, String>,
+    ) {
+        if let Some(name) = remaining.first() {
+            let prompt = format!("{}: ", name);
+            self.start_text_input(&prompt, "", PendingAction::TemplateVariable(t, purpose, remaining, values));
+            return;
+        }
+        match (template::resolve_variables(&t, &values), purpose) {
+            (Ok(resolved), TemplateUse::Launch) => {
+                let name = resolved.template.name.clone();
+                self.start_text_input(
+                    "Session name: ",
+                    &name,
+                    PendingAction::LaunchTemplate(resolved),
+                );
+            }
+            (Ok(resolved), TemplateUse::Export(format)) => {
+                let file = format.file_name(&resolved);
+                self.start_text_input("Export to: ", &file, PendingAction::ExportTemplate(resolved, format));
+            }
+            (Ok(resolved), TemplateUse::Apply { session, prune }) => {
+                match template::apply_template(self.tmux(), &resolved, &session, prune) {
+                    Ok(changes) if changes.is_empty() => {
+                        self.set_status(format!("Session '{}' already matches the template", session), false);
+                    }
+                    Ok(changes) => {
+                        self.set_status(
+                            format!("Applied '{}' to '{}': {}", resolved.template.name, session, changes.join("; ")),
+                            false,
                         );
                     }
+                    Err(e) => self.set_status(e.to_string(), true),
                 }
+                self.refresh_tmux_state();
             }
-            _ => {}
+            (Err(e), _) => self.set_status(e.to_string(), true),
         }
     }
 
-    pub fn selected_template(&self) -> Option<&SessionTemplate> {
+    /// Save a captured session under its template name, asking first if
+    /// that would overwrite an existing template.
+    fn save_captured_template(&mut self, t: SessionTemplate) {
+        let name = t.template.name.clone();
+        if template::template_exists(&name) {
+            return self.start_confirm(
+                &format!("Template '{}' exists. Overwrite? (y/n)", name),
+                PendingAction::OverwriteTemplate(name, t),
+            );
+        }
+        match template::save_template(&t) {
+            Ok(_) => self.set_status(format!("Template '{}' saved", name), false),
+            Err(e) => self.set_status(e.to_string(), true),
+        }
+    }
+
+    /// Export a template or captured session, starting with its format.
+    fn start_export(&mut self, t: SessionTemplate) {
+        self.start_text_input(
+            "Export as (tmuxinator/tmuxp/sh): ",
+            "tmuxinator",
+            PendingAction::ChooseExportFormat(t),
+        );
+    }
+
+    pub fn selected_template_file(&self) -> Option<&TemplateFile> {
         self.template_state
             .selected()
             .and_then(|i| self.templates.get(i))
     }
 
+    /// The selected template, unless its file doesn't parse.
+    pub fn selected_template(&self) -> Option<&SessionTemplate> {
+        self.selected_template_file().and_then(|f| f.template.as_ref())
+    }
+
     pub fn tick(&mut self) {
         self.clear_stale_status();
-        if self.last_refresh.elapsed().as_secs() >= self.refresh_interval_secs {
+        if self.preview_stale {
+            self.refresh_preview();
+        }
+        // With a live control client, changes arrive as notifications; only
+        // poll on the short interval when falling back to one-off processes.
+        // Stale sockets from dead servers have no sessions and don't count.
+        let connected = self
+            .servers
+            .iter()
+            .filter(|b| self.sessions.iter().any(|s| s.server == b.socket()))
+            .all(|b| b.is_control_connected());
+        let interval = if connected {
+            self.idle_refresh_interval_secs
+        } else {
+            self.refresh_interval_secs
+        };
+        if self.last_refresh.elapsed().as_secs() >= interval {
             self.refresh_tmux_state();
         }
     }
 }
+
+/// `msg` followed by the warnings of an import or export, if any.
+fn with_warnings(mut msg: String, warnings: &[String]) -> String {
+    if !warnings.is_empty() {
+        msg.push_str(&format!(" with {} warning(s): {}", warnings.len(), warnings.join("; ")));
+    }
+    msg
+}
+
+/// `done` as a status message, followed by the last line the hook run after
+/// it printed, or its error.
+fn hook_status(done: String, hook: Result<Option<String>>) -> Result<String> {
+    match hook {
+        Ok(Some(line)) => Ok(format!("{}; {}", done, line)),
+        Ok(None) => Ok(done),
+        Err(e) => Err(eyre!("{}, but {}", done, e)),
+    }
+}
+
+/// The scroll that shows the lines `scroll` showed in `old` again in `new`.
+/// Once the capture is as deep as the history kept, output pushes lines out
+/// at the top as well as in at the bottom, so the view is found by its
+/// contents: the nearest match at or above where it was, since lines only
+/// move up. A view that can't be found keeps its scroll.
+fn anchored_scroll(old: &[Line<'static>], new: &[Line<'static>], scroll: usize, rows: usize) -> usize {
+    let end = old.len().saturating_sub(scroll);
+    let view = &old[end.saturating_sub(rows)..end];
+    if view.is_empty() || view.len() > new.len() {
+        return scroll;
+    }
+    let start = (end - view.len()).min(new.len() - view.len());
+    (0..=start)
+        .rev()
+        .find(|&i| new[i..i + view.len()] == *view)
+        .map_or(scroll, |i| new.len() - (i + view.len()))
+}
+
+/// A capture's lines with their styles, without the blank rows below the
+/// pane's last output.
+fn capture_lines(capture: &str) -> Vec<Line<'static>> {
+    let mut lines = ansi::parse(capture);
+    while lines.last().is_some_and(|line| line.width() == 0) {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::tmux::fake::FakeServer;
+
+    fn app_with(server: FakeServer) -> App {
+        App::new(Box::new(server))
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    fn session_names(app: &App) -> Vec<String> {
+        app.sessions.iter().map(|s| s.name.clone()).collect()
+    }
+
+    #[test]
+    fn creates_session_from_prompt() {
+        let mut app = app_with(FakeServer::new());
+
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.mode, InputMode::TextInput);
+        type_text(&mut app, "work");
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(session_names(&app), ["work"]);
+        assert_eq!(app.selected_session().map(|s| s.name).as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn kill_session_requires_confirmation() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        server.new_session("b").unwrap();
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.mode, InputMode::Confirm);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(session_names(&app), ["a", "b"]);
+
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(session_names(&app), ["a"]);
+        assert_eq!(app.selected_session().map(|s| s.name).as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn background_hooks_report_when_done() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        server.set_option("a", None, "@tmmx-on_stop", "\"echo bye\"").unwrap();
+        let mut app = app_with(server);
+        let runs = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&runs);
+        app.enable_background_hooks(Box::new(move |hook: Hook, done| {
+            seen.borrow_mut().push((hook.command, done));
+        }));
+
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(*runs.borrow(), [("echo bye".to_string(), "Session 'a' killed".to_string())]);
+        assert_eq!(app.status.as_ref().unwrap().text, "Session 'a' killed; running its on_stop hook");
+
+        app.on_hook_finished("Session 'a' killed".to_string(), Ok(Some("on_stop: bye".to_string())));
+        assert_eq!(app.status.as_ref().unwrap().text, "Session 'a' killed; on_stop: bye");
+        app.on_hook_finished("Session 'a' killed".to_string(), Err("on_stop hook timed out after 60s".to_string()));
+        let status = app.status.as_ref().unwrap();
+        assert!(status.is_error);
+        assert_eq!(status.text, "Session 'a' killed, but on_stop hook timed out after 60s");
+    }
+
+    #[test]
+    fn filter_narrows_the_list_and_keys_act_on_the_match() {
+        let server = FakeServer::new();
+        for name in ["api", "web", "worker"] {
+            server.new_session(name).unwrap();
+        }
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(app.mode, InputMode::Filter);
+        type_text(&mut app, "wr");
+        let visible: Vec<_> = app.visible_items(&Panel::Sessions).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(visible, [2]);
+        assert_eq!(app.selected_session().map(|s| s.name).as_deref(), Some("worker"));
+
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(session_names(&app), ["api", "web"]);
+        assert!(app.selected_session().is_none());
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.filter, None);
+        assert_eq!(app.selected_session().map(|s| s.name).as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn jump_palette_finds_panes_across_sessions() {
+        let server = FakeServer::new();
+        server.new_session("api").unwrap();
+        server.new_session("web").unwrap();
+        server.new_window("web", Some("logs")).unwrap();
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('f'));
+        assert_eq!(app.mode, InputMode::Jump);
+        assert_eq!(app.jump_targets.len(), 3);
+        type_text(&mut app, "logs");
+        let matches = app.jump_matches();
+        assert_eq!(matches.len(), 1);
+        let target = app.jump_targets[matches[0].0].clone();
+        assert_eq!(target.label(), "web:1.0  logs  bash  /");
+
+        // Enter would also switch the client, which tests can't do.
+        assert!(app.select_jump_target(&target));
+        assert_eq!(app.selected_session().map(|s| s.name).as_deref(), Some("web"));
+        assert_eq!(app.selected_window().map(|w| w.name).as_deref(), Some("logs"));
+        assert_eq!(app.focused, Panel::Panes);
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn scrollback_search_lists_matching_lines_with_context() {
+        let server = FakeServer::new();
+        server.new_session("build").unwrap();
+        server.new_session("shell").unwrap();
+        let build = server.list_panes("build", 0).unwrap()[0].id.clone();
+        let shell = server.list_panes("shell", 0).unwrap()[0].id.clone();
+        server.set_pane_content(&build, "$ cargo build\nerror[E0308]: mismatched types\n --> src/main.rs:3\n");
+        server.set_pane_content(&shell, "$ ls\nERROR.log\n");
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('F'));
+        type_text(&mut app, "error");
+        press(&mut app, KeyCode::Enter);
+        let found: Vec<_> = app.search.hits.iter().map(|h| (h.target.entry.session.as_str(), h.line)).collect();
+        assert_eq!(found, [("build", 1), ("shell", 1)]);
+        let hit = &app.search.hits[0];
+        assert_eq!(hit.context.len(), 3);
+        assert_eq!(hit.text(), "error[E0308]: mismatched types");
+
+        // Tab narrows to the selected session and searches again.
+        press(&mut app, KeyCode::Tab);
+        let found: Vec<_> = app.search.hits.iter().map(|h| h.target.entry.session.as_str()).collect();
+        assert_eq!(found, ["build"]);
+    }
+
+    #[test]
+    fn background_search_takes_hits_as_panes_come_in() {
+        let server = FakeServer::new();
+        server.new_session("build").unwrap();
+        server.new_session("shell").unwrap();
+        let mut app = app_with(server);
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&requests);
+        app.enable_background_search(Box::new(move |search, panes: Vec<(Socket, String)>| {
+            seen.borrow_mut().push((search, panes.len()));
+        }));
+
+        press(&mut app, KeyCode::Char('F'));
+        type_text(&mut app, "error");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(*requests.borrow(), [(1, 2)]);
+        assert_eq!(app.status.as_ref().unwrap().text, "Searching 0/2 panes: 0 match(es)");
+
+        app.on_scrollback(1, 0, Some("$ make\nerror: no rule\n".to_string()));
+        assert_eq!(app.search.hits.len(), 1);
+        assert_eq!(app.search.state.selected(), Some(0));
+        assert_eq!(app.status.as_ref().unwrap().text, "Searching 1/2 panes: 1 match(es)");
+
+        // Tab searches again; what the first search still sends is dropped.
+        press(&mut app, KeyCode::Tab);
+        app.on_scrollback(1, 1, Some("error".to_string()));
+        assert!(app.search.hits.is_empty() && app.search.running());
+        app.on_scrollback(2, 0, None);
+        assert!(!app.search.running());
+        assert_eq!(app.status.as_ref().unwrap().text, "No matches for 'error'");
+    }
+
+    #[test]
+    fn session_selection_cascades_to_windows_and_preview() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        server.new_session("b").unwrap();
+        server.new_window("b", Some("logs")).unwrap();
+        let pane = server.list_panes("b", 0).unwrap()[0].id.clone();
+        server.set_pane_content(&pane, "$ cargo test\n");
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Down);
+
+        let windows: Vec<_> = app.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(windows, ["bash", "logs"]);
+        assert_eq!(app.selected_pane_id(), Some(pane));
+        assert_eq!(app.pane_capture, "$ cargo test\n");
+    }
+
+    #[test]
+    fn preview_scrolls_through_history_and_holds_its_place() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        let pane = server.list_panes("a", 0).unwrap()[0].id.clone();
+        let history: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+        server.set_pane_content(&pane, &history);
+        let mut app = app_with(server);
+        app.set_preview_rows(10);
+
+        press(&mut app, KeyCode::Char('4'));
+        type_text(&mut app, "kkk");
+        assert_eq!(app.preview_scroll, 3);
+        press(&mut app, KeyCode::PageUp);
+        assert_eq!(app.preview_scroll, 13);
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.preview_scroll, 40);
+
+        // New output doesn't move a view that is scrolled back.
+        app.tmux().send_keys(&pane, "line 51").unwrap();
+        app.refresh_tmux_state();
+        assert_eq!(app.preview_lines().len(), 51);
+        assert_eq!(app.preview_scroll, 41);
+        // A taller preview can't scroll back as far.
+        app.set_preview_rows(45);
+        assert_eq!(app.preview_scroll, 6);
+
+        press(&mut app, KeyCode::Char('G'));
+        assert_eq!(app.preview_scroll, 0);
+    }
+
+    #[test]
+    fn preview_holds_its_place_once_history_is_full() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        let pane = server.list_panes("a", 0).unwrap()[0].id.clone();
+        let history: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+        server.set_pane_content(&pane, &history);
+        let mut app = app_with(server);
+        app.set_preview_rows(10);
+        app.set_preview_history(30);
+
+        press(&mut app, KeyCode::Char('4'));
+        type_text(&mut app, "kkk");
+        let top = |app: &App| app.preview_lines()[app.preview_lines().len() - app.preview_scroll - 10].to_string();
+        assert_eq!(top(&app), "line 38");
+
+        // The capture stays 30 lines deep, so line 21 drops out as 51 arrives.
+        app.tmux().send_keys(&pane, "line 51").unwrap();
+        app.refresh_tmux_state();
+        assert_eq!(app.preview_lines().len(), 30);
+        assert_eq!(app.preview_scroll, 4);
+        assert_eq!(top(&app), "line 38");
+    }
+
+    #[test]
+    fn output_bursts_refresh_the_preview_once_per_tick() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        let pane = server.list_panes("a", 0).unwrap()[0].id.clone();
+        let socket = server.socket();
+        let mut app = app_with(server);
+
+        // Just captured, so output waits for the tick.
+        app.tmux().send_keys(&pane, "make").unwrap();
+        app.on_pane_output(&socket, &pane);
+        assert_eq!(app.pane_capture, "");
+        app.tick();
+        assert_eq!(app.pane_capture, "make\n");
+
+        app.preview_refreshed -= PREVIEW_DEBOUNCE;
+        app.tmux().send_keys(&pane, "ok").unwrap();
+        app.on_pane_output(&socket, &pane);
+        assert_eq!(app.pane_capture, "make\nok\n");
+    }
+
+    #[test]
+    fn rename_window_prefills_current_name() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('2'));
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(app.input_buffer, "bash");
+        for _ in 0.."bash".len() {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "editor");
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.selected_window().map(|w| w.name).as_deref(), Some("editor"));
+    }
+
+    #[test]
+    fn split_and_kill_pane() {
+        let server = FakeServer::new();
+        server.new_session("a").unwrap();
+        let mut app = app_with(server);
+
+        press(&mut app, KeyCode::Char('3'));
+        press(&mut app, KeyCode::Char('n'));
+        let widths: Vec<_> = app.panes.iter().map(|p| p.width).collect();
+        assert_eq!(widths, [40, 39]);
+
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.panes.len(), 1);
+    }
+
+    #[test]
+    fn launching_a_template_asks_for_unset_variables() {
+        let mut app = app_with(FakeServer::new());
+        app.templates = vec![TemplateFile {
+            path: PathBuf::from("project.toml"),
+            project: None,
+            template: Some(
+                toml::from_str(
+                    r#"
+                    [template]
+                    name = "${project}"
+                    [template.variables]
+                    root = ""
+                    [[windows]]
+                    name = "code"
+                    cwd = "${root}/${project}"
+                    panes = []
+                    "#,
+                )
+                .unwrap(),
+            ),
+            problems: Vec::new(),
+        }];
+        app.template_state.select(Some(0));
+        app.mode = InputMode::TemplatePicker;
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_prompt, "project: ");
+        type_text(&mut app, "api");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_prompt, "root: ");
+        type_text(&mut app, "/src");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_buffer, "api");
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(session_names(&app), ["api"]);
+        assert_eq!(app.panes[0].cwd, "/src/api");
+    }
+
+    #[test]
+    fn background_launches_report_when_done() {
+        let mut app = app_with(FakeServer::new());
+        let launches = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&launches);
+        app.enable_background_launch(Box::new(move |_, template: SessionTemplate, session| {
+            seen.borrow_mut().push((template.template.name, session));
+        }));
+        let template: SessionTemplate = toml::from_str(
+            r#"
+            [template]
+            name = "web"
+            [[windows]]
+            name = "code"
+            cwd = "/src"
+            panes = []
+            "#,
+        )
+        .unwrap();
+        app.templates = vec![TemplateFile {
+            path: PathBuf::from("web.toml"),
+            project: None,
+            template: Some(template),
+            problems: Vec::new(),
+        }];
+        app.template_state.select(Some(0));
+        app.mode = InputMode::TemplatePicker;
+
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(*launches.borrow(), [("web".to_string(), "web".to_string())]);
+        assert_eq!(app.status.as_ref().unwrap().text, "Launching session 'web' from template");
+
+        app.servers[0].new_session("web").unwrap();
+        app.on_launch_progress("Waiting for window 0 pane 1 of 'web' to be ready (9s left)".to_string());
+        app.on_launched("web", Ok(vec!["started".to_string()]));
+        assert_eq!(app.status.as_ref().unwrap().text, "Session 'web' created from template; started");
+        assert_eq!(session_names(&app), ["web"]);
+
+        app.on_launched("web", Err("Step 3/5 failed".to_string()));
+        assert!(app.status.as_ref().unwrap().is_error);
+    }
+
+    #[test]
+    fn actions_go_to_the_selected_sessions_server() {
+        let one = FakeServer::with_socket(Socket::Name("one".to_string()));
+        one.new_session("dev").unwrap();
+        let two = FakeServer::with_socket(Socket::Name("two".to_string()));
+        two.new_session("dev").unwrap();
+        two.new_session("ops").unwrap();
+        let mut app = app_with(one);
+        app.servers.push(Box::new(two));
+        app.refresh_tmux_state();
+        assert_eq!(session_names(&app), ["dev", "dev", "ops"]);
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.tmux().socket(), Socket::Name("two".to_string()));
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('y'));
+
+        assert_eq!(session_names(&app), ["dev", "ops"]);
+        let servers: Vec<_> = app.sessions.iter().map(|s| s.server.label()).collect();
+        assert_eq!(servers, ["one", "two"]);
+    }
+}
+
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use color_eyre::eyre::{Result, eyre};
+
+use crate::app::PREVIEW_HISTORY;
+use crate::export::ExportFormat;
+use crate::tmux::Socket;
+
+pub const USAGE: &str = "\
+Usage: tmmx [OPTIONS]
+       tmmx import [--force] <FILE>...
+       tmmx launch [--name <SESSION>] [--var <NAME=VALUE>]... [--dry-run] [--no-hooks] <TEMPLATE>
+       tmmx export --format <FORMAT> [--session [--with-options]] [--output <FILE>] [--var <NAME=VALUE>]... <NAME>
+       tmmx template check [<TEMPLATE|FILE>...]
+
+Commands:
+  import <FILE>...          Save tmuxinator or tmuxp project files as templates
+  launch <TEMPLATE>         Start a session from a template, without attaching
+  export <NAME>             Print a template, or with --session a running
+                            session, as tmuxinator, tmuxp or sh
+  template check            Report problems in templates (default: every saved one)
+
+Options:
+  -L, --socket-name <NAME>  Use the tmux server with this socket name
+  -S, --socket-path <PATH>  Use the tmux server at this socket path
+  -a, --all-servers         Also show sessions from every other server
+  -f, --force               With import, overwrite existing templates
+  -F, --format <FORMAT>     With export: tmuxinator, tmuxp or sh
+  -s, --session             With export, <NAME> is a session to capture
+      --with-options        With export --session, keep the session's options and environment
+  -o, --output <FILE>       With export, write to FILE instead of stdout
+  -n, --name <SESSION>      With launch, the session name (default: the template's)
+      --dry-run             With launch, print the tmux commands instead of running them
+      --var <NAME=VALUE>    With launch or export, a value for a template variable
+      --no-hooks            Don't run template hooks (in the TUI, or with launch)
+      --history <LINES>     Lines of scrollback the preview loads (default 1000, 0 for all)
+  -h, --help                Print help
+
+Environment:
+  TMMX_SOCKET_NAME, TMMX_SOCKET_PATH  Defaults for the options above";
+
+pub struct Cli {
+    pub socket: Socket,
+    pub all_servers: bool,
+    /// Whether template hooks run; `--no-hooks` turns them off.
+    pub hooks: bool,
+    /// Scrollback lines the preview loads; 0 loads all of it.
+    pub history: usize,
+    pub help: bool,
+    pub command: Option<Command>,
+}
+
+/// Work done from the command line instead of opening the TUI.
+pub enum Command {
+    Import {
+        files: Vec<PathBuf>,
+        force: bool,
+    },
+    Launch {
+        template: String,
+        session: Option<String>,
+        vars: BTreeMap<String, String>,
+        dry_run: bool,
+    },
+    Export {
+        name: String,
+        session: bool,
+        /// With `session`, capture its options and environment too.
+        with_options: bool,
+        format: ExportFormat,
+        output: Option<PathBuf>,
+        vars: BTreeMap<String, String>,
+    },
+    /// Template names or files to validate; none means every saved template.
+    CheckTemplates {
+        targets: Vec<String>,
+    },
+}
+
+impl Cli {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli {
+            socket: Socket::from_env(),
+            all_servers: false,
+            hooks: true,
+            history: PREVIEW_HISTORY,
+            help: false,
+            command: None,
+        };
+        // Subcommand name, its operands, and its options, checked once all
+        // arguments are in.
+        let mut command: Option<String> = None;
+        let mut operands = Vec::new();
+        let mut force = false;
+        let mut session = false;
+        let mut with_options = false;
+        let mut format = None;
+        let mut output = None;
+        let mut vars = BTreeMap::new();
+        let mut session_name = None;
+        let mut dry_run = false;
+        let mut history = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--opt value` and `--opt=value`.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| eyre!("{} requires a value\n\n{}", flag, USAGE))
+            };
+
+            match flag.as_str() {
+                "-L" | "--socket-name" => cli.socket = Socket::Name(value()?),
+                "-S" | "--socket-path" => cli.socket = Socket::Path(PathBuf::from(value()?)),
+                "-a" | "--all-servers" => cli.all_servers = true,
+                "-h" | "--help" => cli.help = true,
+                "-f" | "--force" => force = true,
+                "-s" | "--session" => session = true,
+                "--with-options" => with_options = true,
+                "-F" | "--format" => format = Some(value()?.parse::<ExportFormat>()?),
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "-n" | "--name" => session_name = Some(value()?),
+                "--dry-run" => dry_run = true,
+                "--no-hooks" => cli.hooks = false,
+                "--history" => {
+                    let lines = value()?;
+                    let lines = lines
+                        .parse()
+                        .map_err(|_| eyre!("--history expects a number of lines, got '{}'", lines))?;
+                    history = Some(lines);
+                }
+                "--var" => {
+                    let var = value()?;
+                    let (name, value) = var
+                        .split_once('=')
+                        .ok_or_else(|| eyre!("--var expects NAME=VALUE, got '{}'", var))?;
+                    vars.insert(name.to_string(), value.to_string());
+                }
+                _ if flag.starts_with('-') => {
+                    return Err(eyre!("unexpected argument '{}'\n\n{}", flag, USAGE));
+                }
+                _ if command.is_none() => command = Some(flag),
+                _ => operands.push(flag),
+            }
+        }
+
+        let only = |allowed: &[&str]| -> Result<()> {
+            let used = [
+                ("--force", force),
+                ("--session", session),
+                ("--with-options", with_options),
+                ("--format", format.is_some()),
+                ("--output", output.is_some()),
+                ("--var", !vars.is_empty()),
+                ("--name", session_name.is_some()),
+                ("--dry-run", dry_run),
+                ("--no-hooks", !cli.hooks),
+                ("--history", history.is_some()),
+            ];
+            match used.iter().find(|(name, set)| *set && !allowed.contains(name)) {
+                Some((name, _)) => Err(eyre!("{} can't be used here\n\n{}", name, USAGE)),
+                None => Ok(()),
+            }
+        };
+        cli.command = match command.as_deref() {
+            None => {
+                only(&["--no-hooks", "--history"])?;
+                None
+            }
+            Some("import") => {
+                only(&["--force"])?;
+                if operands.is_empty() {
+                    return Err(eyre!("import requires at least one file\n\n{}", USAGE));
+                }
+                Some(Command::Import {
+                    files: operands.into_iter().map(PathBuf::from).collect(),
+                    force,
+                })
+            }
+            Some("launch") => {
+                only(&["--name", "--var", "--dry-run", "--no-hooks"])?;
+                let [template] = <[String; 1]>::try_from(operands)
+                    .map_err(|_| eyre!("launch takes one template name\n\n{}", USAGE))?;
+                Some(Command::Launch {
+                    template,
+                    session: session_name,
+                    vars,
+                    dry_run,
+                })
+            }
+            Some("export") => {
+                only(&["--session", "--with-options", "--format", "--output", "--var"])?;
+                if with_options && !session {
+                    return Err(eyre!("--with-options requires --session\n\n{}", USAGE));
+                }
+                let [name] = <[String; 1]>::try_from(operands)
+                    .map_err(|_| eyre!("export takes one template or session name\n\n{}", USAGE))?;
+                Some(Command::Export {
+                    name,
+                    session,
+                    with_options,
+                    format: format.ok_or_else(|| eyre!("export requires --format\n\n{}", USAGE))?,
+                    output,
+                    vars,
+                })
+            }
+            Some("template") => {
+                only(&[])?;
+                match operands.split_first() {
+                    Some((sub, targets)) if sub == "check" => Some(Command::CheckTemplates {
+                        targets: targets.to_vec(),
+                    }),
+                    _ => return Err(eyre!("template expects a subcommand: check\n\n{}", USAGE)),
+                }
+            }
+            Some(other) => return Err(eyre!("unknown command '{}'\n\n{}", other, USAGE)),
+        };
+
+        if let Some(lines) = history {
+            cli.history = lines;
+        }
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse_from(args.iter().map(|a| a.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{:?} parsed", args),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn options_take_separate_or_inline_values() {
+        let cli = parse(&["-L", "work", "--history=0", "--no-hooks"]).unwrap();
+        assert_eq!(cli.socket, Socket::Name("work".to_string()));
+        assert_eq!(cli.history, 0);
+        assert!(!cli.hooks && cli.command.is_none());
+
+        let cli = parse(&["--socket-path=/tmp/s", "launch", "--var", "root=/src", "--var=a=b=c", "web"]).unwrap();
+        assert_eq!(cli.socket, Socket::Path(PathBuf::from("/tmp/s")));
+        let Some(Command::Launch { template, vars, dry_run, .. }) = cli.command else {
+            panic!("not a launch");
+        };
+        assert_eq!(template, "web");
+        assert!(!dry_run);
+        assert_eq!(vars["root"], "/src");
+        assert_eq!(vars["a"], "b=c");
+
+        assert!(error(&["--name"]).starts_with("--name requires a value"));
+        assert!(error(&["--history", "many"]).starts_with("--history expects a number"));
+        assert!(error(&["launch", "--var", "root", "web"]).starts_with("--var expects NAME=VALUE"));
+    }
+
+    #[test]
+    fn subcommands_only_take_their_own_options() {
+        assert!(error(&["--force"]).starts_with("--force can't be used here"));
+        assert!(error(&["import", "--dry-run", "a.yml"]).starts_with("--dry-run can't be used here"));
+        assert!(error(&["template", "check", "--var", "a=b"]).starts_with("--var can't be used here"));
+        assert!(error(&["launch", "--history", "5", "web"]).starts_with("--history can't be used here"));
+        assert!(error(&["export", "-F", "sh", "--name", "x", "web"]).starts_with("--name can't be used here"));
+        assert!(error(&["frobnicate"]).starts_with("unknown command 'frobnicate'"));
+        assert!(error(&["--bogus"]).starts_with("unexpected argument '--bogus'"));
+
+        let cli = parse(&["import", "-f", "a.yml", "b.json"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Import { files, force: true }) if files.len() == 2));
+        let cli = parse(&["template", "check"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::CheckTemplates { targets }) if targets.is_empty()));
+    }
+
+    #[test]
+    fn export_needs_a_format_and_session_for_its_options() {
+        assert!(error(&["export", "web"]).starts_with("export requires --format"));
+        let err = error(&["export", "-F", "sh", "--with-options", "web"]);
+        assert!(err.starts_with("--with-options requires --session"));
+        assert!(error(&["export", "--format=bogus", "web"]).contains("bogus"));
+
+        let cli = parse(&["export", "--format=tmuxp", "-s", "--with-options", "-o", "out.yml", "dev"]).unwrap();
+        let Some(Command::Export { name, session, with_options, format, output, .. }) = cli.command else {
+            panic!("not an export");
+        };
+        assert_eq!((name.as_str(), session, with_options), ("dev", true, true));
+        assert_eq!(format, ExportFormat::Tmuxp);
+        assert_eq!(output, Some(PathBuf::from("out.yml")));
+    }
+}
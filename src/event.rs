@@ -1,46 +1,104 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 
 use color_eyre::eyre::Result;
 use crossterm::event::{self, Event as CrosstermEvent, KeyEvent};
 
+use crate::tmux::{Notification, Socket};
+
+// How long to block on the terminal before checking for tmux notifications.
+const NOTIFY_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, PartialEq)]
 pub enum AppEvent {
     Key(KeyEvent),
     Tick,
     Resize,
+    SessionsChanged,
+    WindowsChanged,
+    /// Window `@id` on the given server.
+    LayoutChanged(Socket, String),
+    /// Pane `%id` on the given server.
+    PaneOutput(Socket, String),
+    LaunchProgress(String),
+    /// Session the launch created, and its hooks' output or the error.
+    Launched(String, Result<Vec<String>, String>),
+    /// The numbered search, which of its panes, and that pane's scrollback.
+    Scrollback(u64, usize, Option<String>),
+    /// The status to show now that a hook has run, and the last line it
+    /// printed or its error.
+    HookFinished(String, Result<Option<String>, String>),
 }
 
 pub struct EventReader {
     tick_rate: Duration,
     last_tick: Instant,
+    notifications: Receiver<(Socket, Notification)>,
+    /// Events from work run off the UI thread: template launches, scrollback
+    /// searches and hooks.
+    background: Receiver<AppEvent>,
+    pending: VecDeque<AppEvent>,
 }
 
 impl EventReader {
-    pub fn new(tick_rate: Duration) -> Self {
+    pub fn new(
+        tick_rate: Duration,
+        notifications: Receiver<(Socket, Notification)>,
+        background: Receiver<AppEvent>,
+    ) -> Self {
         Self {
             tick_rate,
             last_tick: Instant::now(),
+            notifications,
+            background,
+            pending: VecDeque::new(),
         }
     }
 
     pub fn next(&mut self) -> Result<AppEvent> {
-        let timeout = self
-            .tick_rate
-            .checked_sub(self.last_tick.elapsed())
-            .unwrap_or(Duration::ZERO);
-
-        if event::poll(timeout)? {
-            match event::read()? {
-                CrosstermEvent::Key(key) => return Ok(AppEvent::Key(key)),
-                CrosstermEvent::Resize(_, _) => return Ok(AppEvent::Resize),
-                _ => {}
+        loop {
+            self.drain_notifications();
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            let timeout = self
+                .tick_rate
+                .checked_sub(self.last_tick.elapsed())
+                .unwrap_or(Duration::ZERO)
+                .min(NOTIFY_POLL);
+
+            if event::poll(timeout)? {
+                match event::read()? {
+                    CrosstermEvent::Key(key) => return Ok(AppEvent::Key(key)),
+                    CrosstermEvent::Resize(_, _) => return Ok(AppEvent::Resize),
+                    _ => {}
+                }
             }
-        }
 
-        if self.last_tick.elapsed() >= self.tick_rate {
-            self.last_tick = Instant::now();
-            return Ok(AppEvent::Tick);
+            if self.last_tick.elapsed() >= self.tick_rate {
+                self.last_tick = Instant::now();
+                return Ok(AppEvent::Tick);
+            }
         }
+    }
 
-        Ok(AppEvent::Tick)
+    /// Move queued notifications into `pending`, dropping duplicates so a
+    /// burst of `%output` lines costs a single preview refresh, then the
+    /// background events, which all count.
+    fn drain_notifications(&mut self) {
+        for (socket, n) in self.notifications.try_iter() {
+            let event = match n {
+                Notification::SessionsChanged => AppEvent::SessionsChanged,
+                Notification::WindowsChanged => AppEvent::WindowsChanged,
+                Notification::LayoutChanged(id) => AppEvent::LayoutChanged(socket, id),
+                Notification::Output(id) => AppEvent::PaneOutput(socket, id),
+            };
+            if !self.pending.contains(&event) {
+                self.pending.push_back(event);
+            }
+        }
+        self.pending.extend(self.background.try_iter());
     }
 }
@@ -0,0 +1,624 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use color_eyre::eyre::{Result, eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::template::{Hook, LaunchStep, PaneTemplate, SessionTemplate, SplitType, WindowTemplate, plan_launch};
+use crate::tmux::quote;
+
+/// Formats a template can be written out in for people without tmmx.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Tmuxinator,
+    Tmuxp,
+    /// A POSIX shell script of plain `tmux` commands. Waiting for a port
+    /// needs `nc`.
+    Shell,
+}
+
+impl ExportFormat {
+    /// A file name for `template` in this format, in the current directory.
+    pub fn file_name(self, template: &SessionTemplate) -> String {
+        let name = &template.template.name;
+        match self {
+            ExportFormat::Tmuxinator => format!("{}.yml", name),
+            ExportFormat::Tmuxp => format!("{}.tmuxp.yaml", name),
+            ExportFormat::Shell => format!("{}.sh", name),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Tmuxinator => "tmuxinator",
+            ExportFormat::Tmuxp => "tmuxp",
+            ExportFormat::Shell => "sh",
+        })
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "tmuxinator" => Ok(ExportFormat::Tmuxinator),
+            "tmuxp" => Ok(ExportFormat::Tmuxp),
+            "sh" | "shell" => Ok(ExportFormat::Shell),
+            other => Err(eyre!("Unknown export format '{}' (expected tmuxinator, tmuxp or sh)", other)),
+        }
+    }
+}
+
+/// A rendered template, with one message per setting the format has no
+/// equivalent for.
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+/// Render `template` in `format`. Variables must already be resolved: the
+/// other tools know nothing about `${...}` placeholders.
+pub fn export(template: &SessionTemplate, format: ExportFormat) -> Result<Export> {
+    if template.windows.is_empty() {
+        return Err(eyre!("Template has no windows"));
+    }
+    let mut warnings = Vec::new();
+    let content = match format {
+        ExportFormat::Tmuxinator => serde_yaml::to_string(&tmuxinator(template, &mut warnings))?,
+        ExportFormat::Tmuxp => serde_yaml::to_string(&tmuxp(template, &mut warnings))?,
+        ExportFormat::Shell => shell_script(template, &mut warnings)?,
+    };
+    Ok(Export { content, warnings })
+}
+
+/// Write an export to `path`. Scripts are made executable.
+pub fn write_export(path: &Path, content: &str, format: ExportFormat) -> Result<()> {
+    fs::write(path, content)?;
+    if format == ExportFormat::Shell {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn tmuxinator(template: &SessionTemplate, warnings: &mut Vec<String>) -> Mapping {
+    let format = ExportFormat::Tmuxinator;
+    unsupported("", "option", template.options.keys(), format, warnings);
+    unsupported("", "environment variable", template.environment.keys(), format, warnings);
+    unsupported_ready(template, format, warnings);
+    let root = &template.windows[0].cwd;
+    let mut doc = Mapping::new();
+    doc.insert("name".into(), template.template.name.clone().into());
+    doc.insert("root".into(), root.clone().into());
+    let hooks = [
+        ("on_project_first_start", &template.hooks.on_create),
+        ("on_project_restart", &template.hooks.on_attach),
+        ("on_project_stop", &template.hooks.on_stop),
+    ];
+    for (key, hook) in hooks {
+        if let Some(command) = hook {
+            doc.insert(key.into(), command.clone().into());
+        }
+    }
+
+    let windows = template.windows.iter().map(|win| {
+        let context = format!("window '{}': ", win.name);
+        unsupported(&context, "option", win.options.keys(), format, warnings);
+        let mut settings = Mapping::new();
+        if &win.cwd != root {
+            settings.insert("root".into(), win.cwd.clone().into());
+        }
+        if let Some(layout) = layout(win) {
+            settings.insert("layout".into(), layout.into());
+        }
+        let pre = window_commands(win);
+        if !pre.is_empty() {
+            settings.insert("pre".into(), pre.into());
+        }
+        // tmuxinator has no per-pane directory, so panes elsewhere `cd` first.
+        let panes = win.panes.iter().map(|pane| {
+            let mut lines = Vec::new();
+            if pane.cwd != win.cwd && !pane.cwd.is_empty() {
+                lines.push(format!("cd {}", quote(&pane.cwd)));
+            }
+            lines.extend(pane_commands(pane));
+            match lines.len() {
+                0 => Value::Null,
+                1 => lines.remove(0).into(),
+                _ => lines.into(),
+            }
+        });
+        settings.insert("panes".into(), Value::Sequence(panes.collect()));
+
+        let mut entry = Mapping::new();
+        entry.insert(win.name.clone().into(), settings.into());
+        Value::Mapping(entry)
+    });
+    doc.insert("windows".into(), Value::Sequence(windows.collect()));
+    doc
+}
+
+fn tmuxp(template: &SessionTemplate, warnings: &mut Vec<String>) -> Mapping {
+    let format = ExportFormat::Tmuxp;
+    let hooks = [
+        ("on_create", &template.hooks.on_create),
+        ("on_attach", &template.hooks.on_attach),
+        ("on_stop", &template.hooks.on_stop),
+    ];
+    let hooks = hooks.into_iter().filter(|(_, hook)| hook.is_some()).map(|(name, _)| name);
+    unsupported("", "hook", hooks, format, warnings);
+    unsupported_ready(template, format, warnings);
+    let root = &template.windows[0].cwd;
+    let mut doc = Mapping::new();
+    doc.insert("session_name".into(), template.template.name.clone().into());
+    doc.insert("start_directory".into(), root.clone().into());
+    if !template.options.is_empty() {
+        doc.insert("options".into(), values(&template.options));
+    }
+    if !template.environment.is_empty() {
+        doc.insert("environment".into(), values(&template.environment));
+    }
+
+    let windows = template.windows.iter().map(|win| {
+        let mut settings = Mapping::new();
+        settings.insert("window_name".into(), win.name.clone().into());
+        if &win.cwd != root {
+            settings.insert("start_directory".into(), win.cwd.clone().into());
+        }
+        if let Some(layout) = layout(win) {
+            settings.insert("layout".into(), layout.into());
+        }
+        if !win.options.is_empty() {
+            settings.insert("options".into(), values(&win.options));
+        }
+        let before = window_commands(win);
+        if !before.is_empty() {
+            settings.insert("shell_command_before".into(), before.into());
+        }
+        let panes = win.panes.iter().map(|pane| {
+            let commands = pane_commands(pane);
+            let moved = pane.cwd != win.cwd && !pane.cwd.is_empty();
+            if commands.is_empty() && !moved {
+                return Value::from("blank");
+            }
+            let mut settings = Mapping::new();
+            if moved {
+                settings.insert("start_directory".into(), pane.cwd.clone().into());
+            }
+            settings.insert("shell_command".into(), commands.into());
+            Value::Mapping(settings)
+        });
+        settings.insert("panes".into(), Value::Sequence(panes.collect()));
+        Value::Mapping(settings)
+    });
+    doc.insert("windows".into(), Value::Sequence(windows.collect()));
+    doc
+}
+
+/// `launch_template`'s plan as `tmux` commands. Panes are tracked by the ids
+/// tmux prints, so the script doesn't depend on `base-index`.
+fn shell_script(template: &SessionTemplate, warnings: &mut Vec<String>) -> Result<String> {
+    let name = &template.template.name;
+    let mut out = String::from("#!/bin/sh\n");
+    out.push_str(&format!(
+        "# Recreates the tmux session '{}'. Pass a session name to override it.\n",
+        name.replace('\n', " ")
+    ));
+    if has_port_check(template) {
+        out.push_str("# Waiting for ports needs nc.\n");
+    }
+    out.push_str("set -e\n\n");
+    out.push_str(&format!("session=${{1:-{}}}\n", quote(name)));
+    out.push_str(concat!(
+        "if tmux has-session -t \"=$session\" 2>/dev/null; then\n",
+        "    echo \"tmux session '$session' already exists\" >&2\n",
+        "    exit 1\n",
+        "fi\n",
+    ));
+
+    // Panes are tracked by the ids tmux prints, as `w<window>p<pane>`.
+    let pane = |window: u32, pane: usize| format!("w{}p{}", window, pane);
+    let mut pane_counts: Vec<usize> = Vec::new();
+    for step in plan_launch(template, name)?.steps {
+        match step {
+            LaunchStep::RunHook(hook) => out.push_str(&format!("\n# {} hook\n{}\n", hook.name, hook_command(&hook))),
+            LaunchStep::NewSession { cwd, environment } => {
+                pane_counts.push(1);
+                let environment: String =
+                    environment.iter().map(|(k, v)| format!(" -e {}", quote(&format!("{}={}", k, v)))).collect();
+                out.push_str(&format!(
+                    "\n{}=$(tmux new-session -d -s \"$session\" -P -F '#{{pane_id}}' -c {}{})\n",
+                    pane(0, 0),
+                    quote(&cwd),
+                    environment
+                ));
+            }
+            LaunchStep::SetOption { window: None, name, value } => {
+                out.push_str(&format!("tmux set-option -t \"$session\" {} {}\n", quote(&name), quote(&value)));
+            }
+            LaunchStep::SetOption { window: Some(window), name, value } => {
+                out.push_str(&format!(
+                    "tmux set-option -w -t \"${}\" {} {}\n",
+                    pane(window, 0),
+                    quote(&name),
+                    quote(&value)
+                ));
+            }
+            LaunchStep::UnsetOption { name } => {
+                out.push_str(&format!("tmux set-option -u -t \"$session\" {}\n", quote(&name)));
+            }
+            LaunchStep::RenumberWindows => {
+                out.push_str("tmux move-window -r -t \"$session\"\n");
+            }
+            LaunchStep::RenameWindow { window, name } => {
+                out.push_str(&format!("tmux rename-window -t \"${}\" {}\n", pane(window, 0), quote(&name)));
+            }
+            LaunchStep::NewWindow { window, name, cwd } => {
+                pane_counts.push(1);
+                out.push_str(&format!(
+                    "\n{}=$(tmux new-window -d -t \"$session:\" -P -F '#{{pane_id}}' -n {} -c {})\n",
+                    pane(window, 0),
+                    quote(&name),
+                    quote(&cwd)
+                ));
+            }
+            LaunchStep::SplitWindow { window, direction, cwd } => {
+                let count = &mut pane_counts[window as usize];
+                out.push_str(&format!(
+                    "{}=$(tmux split-window {} -P -F '#{{pane_id}}' -t \"${}\" -c {})\n",
+                    pane(window, *count),
+                    direction,
+                    pane(window, *count - 1),
+                    quote(&cwd)
+                ));
+                *count += 1;
+            }
+            LaunchStep::SelectLayout { window, layout } => {
+                out.push_str(&format!("tmux select-layout -t \"${}\" {}\n", pane(window, 0), quote(&layout)));
+            }
+            LaunchStep::SendKeys { window, pane: i, line } => {
+                out.push_str(&format!(
+                    "tmux send-keys -t \"${p}\" -l {} && tmux send-keys -t \"${p}\" Enter\n",
+                    quote(&line),
+                    p = pane(window, i)
+                ));
+            }
+            LaunchStep::WaitReady { window, pane: i, ready } => {
+                let mut checks = Vec::new();
+                checks.extend(ready.output.as_ref().map(|output| {
+                    let pattern = posix_ere(output).unwrap_or_else(|| {
+                        warnings.push(format!(
+                            "window '{}': pane {}: ready output '{}' uses regex syntax grep -E lacks; check the script",
+                            template.windows[window as usize].name,
+                            i + 1,
+                            output
+                        ));
+                        output.clone()
+                    });
+                    format!("tmux capture-pane -p -t \"${}\" | grep -Eq {}", pane(window, i), quote(&pattern))
+                }));
+                checks.extend(ready.port.map(|port| format!("nc -z localhost {}", port)));
+                checks.extend(ready.file.as_ref().map(|file| format!("[ -e {} ]", quote(file))));
+                out.push_str(&format!(
+                    concat!(
+                        "i=0\n",
+                        "until {checks}; do\n",
+                        "    i=$((i + 1))\n",
+                        "    if [ \"$i\" -gt {timeout} ]; then\n",
+                        "        echo \"window {window}, pane {pane} was not ready after {timeout}s\" >&2\n",
+                        "        exit 1\n",
+                        "    fi\n",
+                        "    sleep 1\n",
+                        "done\n",
+                    ),
+                    checks = checks.join(" && "),
+                    timeout = ready.timeout,
+                    window = window,
+                    pane = i
+                ));
+            }
+        }
+    }
+
+    if let Some(hook) = template.hook("on_attach") {
+        out.push_str(&format!("\n# {} hook\n{}\n", hook.name, hook_command(&hook)));
+    }
+    out.push_str(concat!(
+        "\nif [ -n \"$TMUX\" ]; then\n",
+        "    tmux switch-client -t \"=$session\"\n",
+        "else\n",
+        "    tmux attach-session -t \"=$session\"\n",
+        "fi\n",
+    ));
+    Ok(out)
+}
+
+/// Warn that each of `names`, a kind of setting, was left out of `format`.
+fn unsupported(
+    context: &str,
+    kind: &str,
+    names: impl IntoIterator<Item = impl fmt::Display>,
+    format: ExportFormat,
+    warnings: &mut Vec<String>,
+) {
+    for name in names {
+        warnings.push(format!("{}{} '{}' is not supported by {} and was skipped", context, kind, name, format));
+    }
+}
+
+/// Neither YAML format can wait for a pane, so its ready check is dropped.
+fn unsupported_ready(template: &SessionTemplate, format: ExportFormat, warnings: &mut Vec<String>) {
+    for win in &template.windows {
+        for (i, _) in win.panes.iter().enumerate().filter(|(_, p)| p.ready.is_some()) {
+            warnings.push(format!(
+                "window '{}': pane {}: ready is not supported by {} and was skipped",
+                win.name,
+                i + 1,
+                format
+            ));
+        }
+    }
+}
+
+fn has_port_check(template: &SessionTemplate) -> bool {
+    let panes = template.windows.iter().flat_map(|w| &w.panes);
+    panes.filter_map(|p| p.ready.as_ref()).any(|r| r.port.is_some())
+}
+
+/// `pattern`, a Rust regex, as a POSIX extended regex for `grep -E`. `\d`,
+/// `\s` and `\w` become bracket expressions; `None` when it uses anything
+/// else ERE lacks, such as `\b`, flags or lazy repetition.
+fn posix_ere(pattern: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars.next()?;
+                let class = match escaped {
+                    'd' => Some("0-9"),
+                    's' => Some("[:space:]"),
+                    'w' => Some("[:alnum:]_"),
+                    _ => None,
+                };
+                match class {
+                    Some(class) if in_class => out.push_str(class),
+                    Some(class) => out.push_str(&format!("[{}]", class)),
+                    // A backslash is literal inside an ERE bracket expression.
+                    None if in_class && escaped.is_ascii_punctuation() && !matches!(escaped, ']' | '-') => {
+                        out.push(escaped)
+                    }
+                    None if !in_class && escaped.is_ascii_punctuation() => {
+                        out.push('\\');
+                        out.push(escaped);
+                    }
+                    None => return None,
+                }
+            }
+            '[' if in_class => {
+                out.push(c);
+                // A named class such as `[:digit:]` is spelled the same in both.
+                if chars.peek() == Some(&':') {
+                    for c in chars.by_ref() {
+                        out.push(c);
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+            }
+            '[' => {
+                in_class = true;
+                out.push(c);
+                if chars.peek() == Some(&'^') {
+                    out.push(chars.next()?);
+                }
+                if chars.peek() == Some(&']') {
+                    out.push(chars.next()?);
+                }
+            }
+            ']' if in_class => {
+                in_class = false;
+                out.push(c);
+            }
+            '(' if !in_class && chars.peek() == Some(&'?') => return None,
+            '*' | '+' | '?' | '}' if !in_class && chars.peek() == Some(&'?') => return None,
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// A hook as a subshell in its directory, with the template's environment.
+fn hook_command(hook: &Hook) -> String {
+    let environment: String = hook.environment.iter().map(|(k, v)| format!("{}={} ", k, quote(v))).collect();
+    format!("(cd {} && {}sh -c {})", quote(&hook.cwd), environment, quote(&hook.command))
+}
+
+/// The window's layout, or a named one matching its split hints, since the
+/// other tools apply no layout at all without one.
+fn layout(win: &WindowTemplate) -> Option<String> {
+    if win.layout.is_some() || win.panes.len() < 2 {
+        return win.layout.clone();
+    }
+    let splits: Vec<_> = win.panes.iter().skip(1).map(|p| &p.split).collect();
+    let name = if splits.iter().all(|s| matches!(s, SplitType::Horizontal)) {
+        "even-vertical"
+    } else if splits.iter().all(|s| matches!(s, SplitType::Vertical)) {
+        "even-horizontal"
+    } else {
+        "tiled"
+    };
+    Some(name.to_string())
+}
+
+fn values(map: &BTreeMap<String, String>) -> Value {
+    Value::Mapping(map.iter().map(|(k, v)| (k.clone().into(), v.clone().into())).collect())
+}
+
+fn window_commands(win: &WindowTemplate) -> Vec<String> {
+    win.command.iter().chain(&win.send_keys).cloned().collect()
+}
+
+fn pane_commands(pane: &PaneTemplate) -> Vec<String> {
+    pane.command.iter().chain(&pane.send_keys).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::{Ready, TemplateMeta, launch_template};
+    use crate::tmux::TmuxBackend;
+    use crate::tmux::fake::FakeServer;
+
+    fn sample() -> SessionTemplate {
+        let pane = |cwd: &str, split, command: Option<&str>| PaneTemplate {
+            cwd: cwd.to_string(),
+            split,
+            command: command.map(str::to_string),
+            send_keys: Vec::new(),
+            ready: None,
+        };
+        SessionTemplate {
+            template: TemplateMeta {
+                name: "web".to_string(),
+                description: String::new(),
+                extends: None,
+                variables: Default::default(),
+            },
+            options: BTreeMap::new(),
+            environment: BTreeMap::new(),
+            hooks: Default::default(),
+            windows: vec![
+                WindowTemplate {
+                    include: None,
+                    name: "code".to_string(),
+                    cwd: "/src/web".to_string(),
+                    layout: None,
+                    options: BTreeMap::new(),
+                    command: Some("source .env".to_string()),
+                    send_keys: Vec::new(),
+                    panes: vec![
+                        pane("/src/web", SplitType::Full, Some("vim")),
+                        pane("/src/web/docs", SplitType::Horizontal, Some("make serve")),
+                    ],
+                },
+                WindowTemplate {
+                    include: None,
+                    name: "shell".to_string(),
+                    cwd: "/tmp".to_string(),
+                    layout: None,
+                    options: BTreeMap::new(),
+                    command: None,
+                    send_keys: Vec::new(),
+                    panes: vec![pane("/tmp", SplitType::Full, None)],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn exports_round_trip_through_the_importers() {
+        let dir = std::env::temp_dir().join(format!("tmmx-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for format in [ExportFormat::Tmuxinator, ExportFormat::Tmuxp] {
+            let path = dir.join(format.file_name(&sample()));
+            let exported = export(&sample(), format).unwrap();
+            assert!(exported.warnings.is_empty(), "{}: {:?}", format, exported.warnings);
+            write_export(&path, &exported.content, format).unwrap();
+            let imported = crate::import::import_file(&path).unwrap();
+            assert!(imported.warnings.is_empty(), "{}: {:?}", format, imported.warnings);
+
+            let code = &imported.template.windows[0];
+            assert_eq!(code.cwd, "/src/web");
+            assert_eq!(code.layout.as_deref(), Some("even-vertical"));
+            assert_eq!(code.command.as_deref(), Some("source .env"));
+            assert_eq!(code.panes[0].command.as_deref(), Some("vim"));
+            let docs = &code.panes[1];
+            let moved = format == ExportFormat::Tmuxinator;
+            assert_eq!(docs.cwd, if moved { "/src/web" } else { "/src/web/docs" });
+            assert_eq!(
+                docs.command.as_deref(),
+                Some(if moved { "cd '/src/web/docs'" } else { "make serve" })
+            );
+            assert_eq!(imported.template.windows[1].cwd, "/tmp");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shell_script_mirrors_launch() {
+        let script = export(&sample(), ExportFormat::Shell).unwrap().content;
+        assert!(script.contains("w0p0=$(tmux new-session -d -s \"$session\" -P -F '#{pane_id}' -c '/src/web')"));
+        assert!(script.contains("tmux rename-window -t \"$w0p0\" 'code'"));
+        assert!(script.contains("w0p1=$(tmux split-window -v -P -F '#{pane_id}' -t \"$w0p0\" -c '/src/web/docs')"));
+        assert!(script.contains("tmux send-keys -t \"$w0p1\" -l 'source .env' && tmux send-keys -t \"$w0p1\" Enter\n"));
+        assert!(script.contains("w1p0=$(tmux new-window -d -t \"$session:\" -P -F '#{pane_id}' -n 'shell' -c '/tmp')"));
+
+        // The same panes as a real launch, in the same order.
+        let tmux = FakeServer::new();
+        launch_template(&tmux, &sample(), "web").unwrap();
+        assert_eq!(tmux.list_panes("web", 0).unwrap().len(), script.matches("=$(tmux split-window").count() + 1);
+    }
+
+    #[test]
+    fn settings_a_format_lacks_are_reported() {
+        let mut template = sample();
+        template.options.insert("mouse".to_string(), "on".to_string());
+        template.environment.insert("PORT".to_string(), "8080".to_string());
+        template.hooks.on_create = Some("make deps".to_string());
+        template.windows[0].options.insert("synchronize-panes".to_string(), "on".to_string());
+        template.windows[0].panes[1].ready = Some(Ready {
+            output: Some(r"server --port \d+".to_string()),
+            port: Some(8080),
+            file: None,
+            timeout: 30,
+        });
+
+        assert_eq!(
+            export(&template, ExportFormat::Tmuxinator).unwrap().warnings,
+            [
+                "option 'mouse' is not supported by tmuxinator and was skipped",
+                "environment variable 'PORT' is not supported by tmuxinator and was skipped",
+                "window 'code': pane 2: ready is not supported by tmuxinator and was skipped",
+                "window 'code': option 'synchronize-panes' is not supported by tmuxinator and was skipped",
+            ]
+        );
+        assert_eq!(
+            export(&template, ExportFormat::Tmuxp).unwrap().warnings,
+            [
+                "hook 'on_create' is not supported by tmuxp and was skipped",
+                "window 'code': pane 2: ready is not supported by tmuxp and was skipped",
+            ]
+        );
+
+        let script = export(&template, ExportFormat::Shell).unwrap();
+        assert!(script.warnings.is_empty(), "{:?}", script.warnings);
+        assert!(script.content.contains("grep -Eq 'server --port [0-9]+'"));
+        assert!(script.content.contains("# Waiting for ports needs nc.\n"));
+
+        template.windows[0].panes[1].ready.as_mut().unwrap().output = Some(r"\bready\b".to_string());
+        assert_eq!(
+            export(&template, ExportFormat::Shell).unwrap().warnings,
+            [r"window 'code': pane 2: ready output '\bready\b' uses regex syntax grep -E lacks; check the script"]
+        );
+    }
+
+    #[test]
+    fn ready_patterns_become_extended_regexes() {
+        assert_eq!(posix_ere(r"^\w+:\s*\d\.\d$").as_deref(), Some(r"^[[:alnum:]_]+:[[:space:]]*[0-9]\.[0-9]$"));
+        assert_eq!(posix_ere(r"[\d.]+ [[:alpha:]\]]").as_deref(), None);
+        assert_eq!(posix_ere(r"[^\d.]+ [[:alpha:]]").as_deref(), Some(r"[^0-9.]+ [[:alpha:]]"));
+        for rust_only in [r"\bready\b", "(?i)ready", "a+?", r"\pL"] {
+            assert_eq!(posix_ere(rust_only), None, "{}", rust_only);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// Where `query` matches `text`, ignoring case, as char positions in `text`:
+/// the first run of consecutive characters if there is one, else the
+/// earliest positions that spell the query in order. `None` if `text` doesn't
+/// hold the query's characters in order; an empty query matches anything.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Vec<usize>> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let text: Vec<char> = text.chars().map(fold).collect();
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() {
+        return Some(Vec::new());
+    }
+    if let Some(start) = text.windows(query.len()).position(|run| run == query.as_slice()) {
+        return Some((start..start + query.len()).collect());
+    }
+    let mut chars = text.iter().enumerate();
+    query
+        .iter()
+        .map(|q| chars.find(|(_, c)| *c == q).map(|(i, _)| i))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_a_consecutive_run_over_scattered_letters() {
+        assert_eq!(fuzzy_match("", "api"), Some(vec![]));
+        assert_eq!(fuzzy_match("Web", "my-web"), Some(vec![3, 4, 5]));
+        assert_eq!(fuzzy_match("wkr", "worker"), Some(vec![0, 3, 5]));
+        assert_eq!(fuzzy_match("or", "o-worker"), Some(vec![3, 4]));
+        assert_eq!(fuzzy_match("wr", "web"), None);
+        assert_eq!(fuzzy_match("apis", "api"), None);
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::{Result, eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::layout::{self, LayoutNode};
+use crate::template::{Hooks, PaneTemplate, SessionTemplate, SplitType, TemplateMeta, WindowTemplate};
+
+/// A converted project file, with one message per setting that had no
+/// equivalent in a template and was left out.
+pub struct Import {
+    pub template: SessionTemplate,
+    pub warnings: Vec<String>,
+}
+
+/// Read a tmuxinator or tmuxp project file. tmuxp files are told apart by
+/// their `session_name` key; `.json` files are read as JSON, anything else as
+/// YAML.
+pub fn import_file(path: &Path) -> Result<Import> {
+    let content = fs::read_to_string(path).map_err(|e| eyre!("Cannot read {}: {}", path.display(), e))?;
+    let doc: Value = if path.extension().is_some_and(|e| e == "json") {
+        serde_json::from_str(&content)?
+    } else {
+        serde_yaml::from_str(&content)?
+    };
+    let doc = doc
+        .as_mapping()
+        .ok_or_else(|| eyre!("{} is not a tmuxinator or tmuxp project", path.display()))?;
+
+    let mut warnings = Vec::new();
+    if content.contains("<%") {
+        warnings.push("ERB tags (<% %>) are not evaluated; check the imported values".to_string());
+    }
+    let template = if doc.contains_key("session_name") {
+        from_tmuxp(doc, &mut warnings)?
+    } else if doc.contains_key("windows") || doc.contains_key("tabs") {
+        from_tmuxinator(doc, &mut warnings)?
+    } else {
+        return Err(eyre!("{} is not a tmuxinator or tmuxp project", path.display()));
+    };
+    Ok(Import { template, warnings })
+}
+
+/// Expand a leading `~/` the way a shell would, for paths typed into prompts.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home.display(), rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn from_tmuxinator(doc: &Mapping, warnings: &mut Vec<String>) -> Result<SessionTemplate> {
+    let name = field(doc, &["name", "project_name"])
+        .and_then(text)
+        .map(|n| escape(&n))
+        .ok_or_else(|| eyre!("tmuxinator project has no name"))?;
+    let root = field(doc, &["root", "project_root"])
+        .and_then(text)
+        .map_or_else(|| "${env:PWD}".to_string(), |r| directory(&r, ""));
+    let pre_window = field(doc, &["pre_window", "pre_tab"]).map(lines).unwrap_or_default();
+    // `on_project_start` runs on every start, before `on_project_first_start`.
+    let starts: Vec<_> = ["on_project_start", "on_project_first_start"]
+        .iter()
+        .flat_map(|key| doc.get(*key).map(lines).unwrap_or_default())
+        .collect();
+    let hooks = Hooks {
+        on_create: (!starts.is_empty()).then(|| starts.join("\n")),
+        on_attach: doc.get("on_project_restart").map(|v| lines(v).join("\n")),
+        on_stop: doc.get("on_project_stop").map(|v| lines(v).join("\n")),
+    };
+    skipped(
+        doc,
+        &[
+            "name",
+            "project_name",
+            "root",
+            "project_root",
+            "pre_window",
+            "pre_tab",
+            "on_project_start",
+            "on_project_first_start",
+            "on_project_restart",
+            "on_project_stop",
+            "windows",
+            "tabs",
+        ],
+        "",
+        warnings,
+    );
+
+    let mut windows = Vec::new();
+    for entry in sequence(field(doc, &["windows", "tabs"]), "windows")? {
+        // Each window is a single-key map from its name to its contents.
+        let Some((name, value)) = entry.as_mapping().and_then(|m| m.iter().next()) else {
+            return Err(eyre!("tmuxinator window {:?} is not a `name: ...` entry", entry));
+        };
+        let name = escape(&text(name).unwrap_or_default());
+        let context = format!("window '{}': ", name);
+
+        let mut window = WindowTemplate {
+            include: None,
+            name,
+            cwd: root.clone(),
+            layout: None,
+            options: BTreeMap::new(),
+            command: None,
+            send_keys: Vec::new(),
+            panes: Vec::new(),
+        };
+        let mut commands = pre_window.clone();
+        let mut panes = Vec::new();
+        match value {
+            Value::Mapping(settings) => {
+                if let Some(dir) = settings.get("root").and_then(text) {
+                    window.cwd = directory(&dir, &root);
+                }
+                window.layout = settings.get("layout").and_then(text).and_then(|l| checked_layout(l, &context, warnings));
+                commands.extend(settings.get("pre").map(lines).unwrap_or_default());
+                for pane in sequence(settings.get("panes"), "panes")? {
+                    panes.push(match pane {
+                        // A titled pane: `- title: [commands]`.
+                        Value::Mapping(m) => m.iter().next().map(|(_, v)| lines(v)).unwrap_or_default(),
+                        other => lines(other),
+                    });
+                }
+                skipped(settings, &["root", "layout", "pre", "panes"], &context, warnings);
+            }
+            other => panes.push(lines(other)),
+        }
+        if panes.is_empty() {
+            panes.push(Vec::new());
+        }
+
+        (window.command, window.send_keys) = split_first(commands);
+        window.panes = build_panes(panes.into_iter().map(|p| (window.cwd.clone(), p)));
+        windows.push(window);
+    }
+
+    let mut template = template(name, "tmuxinator", windows);
+    template.hooks = hooks;
+    Ok(template)
+}
+
+fn from_tmuxp(doc: &Mapping, warnings: &mut Vec<String>) -> Result<SessionTemplate> {
+    let name = doc
+        .get("session_name")
+        .and_then(text)
+        .map(|n| escape(&n))
+        .ok_or_else(|| eyre!("tmuxp session has no name"))?;
+    let root = doc
+        .get("start_directory")
+        .and_then(text)
+        .map_or_else(|| "${env:PWD}".to_string(), |r| directory(&r, ""));
+    let before = doc.get("shell_command_before").map(shell_commands).unwrap_or_default();
+    // tmuxp runs it from the start directory before creating the session.
+    let before_script = doc.get("before_script").and_then(text).map(|script| escape(&script));
+    let options = doc.get("options").map(settings).unwrap_or_default();
+    let environment = doc.get("environment").map(settings).unwrap_or_default();
+    skipped(
+        doc,
+        &[
+            "session_name",
+            "start_directory",
+            "before_script",
+            "shell_command_before",
+            "options",
+            "environment",
+            "windows",
+        ],
+        "",
+        warnings,
+    );
+
+    let mut windows = Vec::new();
+    for (i, entry) in sequence(doc.get("windows"), "windows")?.iter().enumerate() {
+        let settings = entry
+            .as_mapping()
+            .ok_or_else(|| eyre!("tmuxp window {:?} is not a mapping", entry))?;
+        let name = settings
+            .get("window_name")
+            .and_then(text)
+            .map(|n| escape(&n))
+            .unwrap_or_else(|| (i + 1).to_string());
+        let context = format!("window '{}': ", name);
+        let cwd = settings
+            .get("start_directory")
+            .and_then(text)
+            .map_or_else(|| root.clone(), |d| directory(&d, &root));
+        let layout = settings.get("layout").and_then(text).and_then(|l| checked_layout(l, &context, warnings));
+
+        let mut commands = before.clone();
+        commands.extend(settings.get("shell_command_before").map(shell_commands).unwrap_or_default());
+        let (command, send_keys) = split_first(commands);
+
+        let mut panes = Vec::new();
+        for pane in sequence(settings.get("panes"), "panes")? {
+            panes.push(match pane {
+                Value::Mapping(m) => {
+                    let pane_context = format!("{}pane {}: ", context, panes.len() + 1);
+                    skipped(m, &["shell_command", "start_directory"], &pane_context, warnings);
+                    let dir = m.get("start_directory").and_then(text);
+                    (
+                        dir.map_or_else(|| cwd.clone(), |d| directory(&d, &cwd)),
+                        m.get("shell_command").map(shell_commands).unwrap_or_default(),
+                    )
+                }
+                // tmuxp's placeholders for an empty pane.
+                Value::String(s) if s == "blank" || s == "pane" => (cwd.clone(), Vec::new()),
+                other => (cwd.clone(), shell_commands(other)),
+            });
+        }
+        if panes.is_empty() {
+            panes.push((cwd.clone(), Vec::new()));
+        }
+        skipped(
+            settings,
+            &["window_name", "start_directory", "layout", "options", "shell_command_before", "panes"],
+            &context,
+            warnings,
+        );
+
+        windows.push(WindowTemplate {
+            include: None,
+            name,
+            cwd,
+            layout,
+            options: settings.get("options").map(self::settings).unwrap_or_default(),
+            command,
+            send_keys,
+            panes: build_panes(panes.into_iter()),
+        });
+    }
+
+    let mut template = template(name, "tmuxp", windows);
+    template.options = options;
+    template.environment = environment;
+    template.hooks.on_create = before_script;
+    Ok(template)
+}
+
+fn template(name: String, tool: &str, windows: Vec<WindowTemplate>) -> SessionTemplate {
+    SessionTemplate {
+        template: TemplateMeta {
+            name,
+            description: format!("Imported from {}", tool),
+            extends: None,
+            variables: Default::default(),
+        },
+        options: BTreeMap::new(),
+        environment: BTreeMap::new(),
+        hooks: Hooks::default(),
+        windows,
+    }
+}
+
+/// Panes after the first are stacked below it, as a bare `split-window` would;
+/// the window's layout rearranges them afterwards.
+fn build_panes(panes: impl Iterator<Item = (String, Vec<String>)>) -> Vec<PaneTemplate> {
+    panes
+        .enumerate()
+        .map(|(i, (cwd, commands))| {
+            let (command, send_keys) = split_first(commands);
+            PaneTemplate {
+                cwd,
+                split: if i == 0 { SplitType::Full } else { SplitType::Horizontal },
+                command,
+                send_keys,
+                ready: None,
+            }
+        })
+        .collect()
+}
+
+fn split_first(mut lines: Vec<String>) -> (Option<String>, Vec<String>) {
+    if lines.is_empty() {
+        (None, lines)
+    } else {
+        let first = lines.remove(0);
+        (Some(first), lines)
+    }
+}
+
+/// Named layouts pass through; anything else must be a valid layout string.
+fn checked_layout(layout: String, context: &str, warnings: &mut Vec<String>) -> Option<String> {
+    if layout::PRESETS.contains(&layout.as_str()) || LayoutNode::parse_unchecked(&layout).is_ok() {
+        Some(layout)
+    } else {
+        warnings.push(format!("{}layout '{}' is not a tmux layout and was skipped", context, layout));
+        None
+    }
+}
+
+fn skipped(map: &Mapping, known: &[&str], context: &str, warnings: &mut Vec<String>) {
+    for key in map.keys().filter_map(text) {
+        if !known.contains(&key.as_str()) {
+            warnings.push(format!("{}'{}' is not supported and was skipped", context, key));
+        }
+    }
+}
+
+fn field<'a>(map: &'a Mapping, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|n| map.get(*n))
+}
+
+fn sequence<'a>(value: Option<&'a Value>, what: &str) -> Result<&'a [Value]> {
+    match value {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Sequence(items)) => Ok(items),
+        Some(other) => Err(eyre!("Expected a list of {}, found {:?}", what, other)),
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// tmuxp's `options` and `environment` maps, with YAML booleans as tmux's
+/// `on` and `off`.
+fn settings(value: &Value) -> BTreeMap<String, String> {
+    let Some(map) = value.as_mapping() else {
+        return BTreeMap::new();
+    };
+    map.iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Value::Bool(flag) => if *flag { "on" } else { "off" }.to_string(),
+                other => text(other)?,
+            };
+            Some((text(name)?, escape(&value)))
+        })
+        .collect()
+}
+
+/// A command or list of commands, escaped so template variables leave them
+/// alone.
+fn lines(value: &Value) -> Vec<String> {
+    match value {
+        Value::Sequence(items) => items.iter().filter_map(text).map(|l| escape(&l)).collect(),
+        other => text(other).map(|l| vec![escape(&l)]).unwrap_or_default(),
+    }
+}
+
+/// tmuxp's `shell_command`: a string, or a list of strings and `{cmd: ...}`
+/// maps.
+fn shell_commands(value: &Value) -> Vec<String> {
+    match value {
+        Value::Sequence(items) => items
+            .iter()
+            .filter_map(|item| text(item).or_else(|| item.get("cmd").and_then(text)))
+            .map(|l| escape(&l))
+            .collect(),
+        other => lines(other),
+    }
+}
+
+/// `$${` is a literal `${` in templates.
+fn escape(text: &str) -> String {
+    text.replace("${", "$${")
+}
+
+/// A project directory as a template `cwd`. tmux doesn't expand `~` or `$VAR`
+/// in `-c`, so both become `${env:...}` placeholders; relative paths are
+/// taken from `base`.
+fn directory(path: &str, base: &str) -> String {
+    let mut out = String::new();
+    let mut rest = path;
+    if let Some(after) = rest.strip_prefix('~')
+        && (after.is_empty() || after.starts_with('/'))
+    {
+        out.push_str("${env:HOME}");
+        rest = after;
+    }
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (var, tail) = match after.strip_prefix('{').and_then(|a| a.split_once('}')) {
+            Some((var, tail)) => (var, tail),
+            None => {
+                let len = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                after.split_at(len)
+            }
+        };
+        if var.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(&format!("${{env:{}}}", var));
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+
+    if out.starts_with('/') || out.starts_with("${") || base.is_empty() {
+        out
+    } else if out == "." {
+        base.to_string()
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), out.trim_start_matches("./"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(name: &str, content: &str) -> Import {
+        let path = std::env::temp_dir().join(format!("tmmx-import-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        let result = import_file(&path);
+        fs::remove_file(&path).unwrap();
+        result.unwrap()
+    }
+
+    #[test]
+    fn imports_tmuxinator_project() {
+        let Import { template, warnings } = import(
+            "project.yml",
+            "\
+name: blog
+root: ~/code/blog
+pre_window: source .env
+on_project_start: docker compose up -d
+windows:
+  - editor:
+      layout: main-vertical
+      panes:
+        - vim
+        - logs:
+          - cd log
+          - tail -f ${LOG}
+  - server: bundle exec rails s
+  - console:
+      root: api
+      synchronize: true
+",
+        );
+
+        assert_eq!(template.template.name, "blog");
+        let editor = &template.windows[0];
+        assert_eq!(editor.cwd, "${env:HOME}/code/blog");
+        assert_eq!(editor.layout.as_deref(), Some("main-vertical"));
+        assert_eq!(editor.command.as_deref(), Some("source .env"));
+        assert_eq!(editor.panes[0].command.as_deref(), Some("vim"));
+        assert_eq!(editor.panes[1].command.as_deref(), Some("cd log"));
+        assert_eq!(editor.panes[1].send_keys, ["tail -f $${LOG}"]);
+        assert_eq!(template.windows[1].panes[0].command.as_deref(), Some("bundle exec rails s"));
+        assert_eq!(template.windows[2].cwd, "${env:HOME}/code/blog/api");
+        assert_eq!(template.windows[2].panes.len(), 1);
+        assert_eq!(template.hooks.on_create.as_deref(), Some("docker compose up -d"));
+        assert_eq!(warnings, ["window 'console': 'synchronize' is not supported and was skipped"]);
+    }
+
+    #[test]
+    fn imports_tmuxp_session_from_json() {
+        let Import { template, warnings } = import(
+            "session.json",
+            r#"{
+  "session_name": "api",
+  "start_directory": "$PROJECTS/api",
+  "shell_command_before": ["nvm use"],
+  "windows": [
+    {
+      "window_name": "dev",
+      "layout": "not-a-layout",
+      "panes": [
+        {"shell_command": ["make watch", {"cmd": "echo ready"}]},
+        {"start_directory": "docs", "shell_command": "mkdocs serve", "focus": true},
+        "blank"
+      ]
+    },
+    {"panes": []}
+  ]
+}"#,
+        );
+
+        let dev = &template.windows[0];
+        assert_eq!(dev.cwd, "${env:PROJECTS}/api");
+        assert_eq!(dev.layout, None);
+        assert_eq!(dev.command.as_deref(), Some("nvm use"));
+        assert_eq!(dev.panes[0].command.as_deref(), Some("make watch"));
+        assert_eq!(dev.panes[0].send_keys, ["echo ready"]);
+        assert_eq!(dev.panes[1].cwd, "${env:PROJECTS}/api/docs");
+        assert_eq!(dev.panes[2].command, None);
+        assert_eq!(template.windows[1].name, "2");
+        assert_eq!(template.windows[1].panes.len(), 1);
+        assert_eq!(
+            warnings,
+            [
+                "window 'dev': layout 'not-a-layout' is not a tmux layout and was skipped",
+                "window 'dev': pane 2: 'focus' is not supported and was skipped",
+            ]
+        );
+    }
+}
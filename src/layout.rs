@@ -0,0 +1,340 @@
+use std::fmt;
+
+use color_eyre::eyre::{Result, eyre};
+
+/// tmux's named layouts, accepted by `select-layout` in place of a layout
+/// string.
+pub const PRESETS: [&str; 5] = [
+    "even-horizontal",
+    "even-vertical",
+    "main-horizontal",
+    "main-vertical",
+    "tiled",
+];
+
+/// A tmux window layout as a split tree, as found in `#{window_layout}`:
+/// `csum,WxH,X,Y` followed by a pane id, or by `{...}` (side by side) or
+/// `[...]` (stacked) holding the children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutNode {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+    pub kind: LayoutKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutKind {
+    /// A pane, by its numeric id (`%3` is 3). Layouts from tmux before 2.1
+    /// don't carry ids.
+    Pane(Option<u32>),
+    /// `{}`: children left to right.
+    Horizontal(Vec<LayoutNode>),
+    /// `[]`: children top to bottom.
+    Vertical(Vec<LayoutNode>),
+}
+
+impl LayoutNode {
+    /// Parse a layout string as tmux prints it. The checksum must match.
+    pub fn parse(layout: &str) -> Result<Self> {
+        let (csum, body) = split_checksum(layout).ok_or_else(|| eyre!("invalid layout: missing checksum"))?;
+        if csum != checksum(body) {
+            return Err(eyre!("invalid layout: checksum mismatch"));
+        }
+        Self::parse_body(body)
+    }
+
+    /// Parse a layout string that may have been edited by hand: a checksum is
+    /// optional and not verified. `to_string` produces a correct one again.
+    pub fn parse_unchecked(layout: &str) -> Result<Self> {
+        let body = split_checksum(layout).map_or(layout, |(_, body)| body);
+        Self::parse_body(body)
+    }
+
+    fn parse_body(body: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input: body.as_bytes(),
+            pos: 0,
+        };
+        let node = parser.node()?;
+        if parser.pos != body.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        node.check()?;
+        Ok(node)
+    }
+
+    /// Leaf cells in the order tmux assigns panes to them.
+    pub fn panes(&self) -> Vec<&LayoutNode> {
+        match &self.kind {
+            LayoutKind::Pane(_) => vec![self],
+            LayoutKind::Horizontal(children) | LayoutKind::Vertical(children) => {
+                children.iter().flat_map(|c| c.panes()).collect()
+            }
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.panes().len()
+    }
+
+    /// Compact shape of the split tree, e.g. `{%1 [%2 %3]}`.
+    pub fn shape(&self) -> String {
+        match &self.kind {
+            LayoutKind::Pane(Some(id)) => format!("%{}", id),
+            LayoutKind::Pane(None) => "?".to_string(),
+            LayoutKind::Horizontal(children) => format!("{{{}}}", shape_list(children)),
+            LayoutKind::Vertical(children) => format!("[{}]", shape_list(children)),
+        }
+    }
+
+    /// Whether both layouts have the same cells, whatever panes fill them.
+    pub fn same_geometry(&self, other: &LayoutNode) -> bool {
+        let cells = |n: &LayoutNode| (n.width, n.height, n.x, n.y);
+        cells(self) == cells(other)
+            && match (&self.kind, &other.kind) {
+                (LayoutKind::Pane(_), LayoutKind::Pane(_)) => true,
+                (LayoutKind::Horizontal(a), LayoutKind::Horizontal(b))
+                | (LayoutKind::Vertical(a), LayoutKind::Vertical(b)) => {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_geometry(b))
+                }
+                _ => false,
+            }
+    }
+
+    /// Children must tile their parent exactly, with a one-cell border
+    /// between neighbours, or tmux rejects the layout.
+    fn check(&self) -> Result<()> {
+        let (children, horizontal) = match &self.kind {
+            LayoutKind::Pane(_) => return Ok(()),
+            LayoutKind::Horizontal(c) => (c, true),
+            LayoutKind::Vertical(c) => (c, false),
+        };
+        if children.is_empty() {
+            return Err(eyre!("invalid layout: empty split at {},{}", self.x, self.y));
+        }
+
+        let (mut x, mut y) = (self.x, self.y);
+        for child in children {
+            let fits = if horizontal {
+                child.x == x && child.y == self.y && child.height == self.height
+            } else {
+                child.y == y && child.x == self.x && child.width == self.width
+            };
+            if !fits {
+                return Err(eyre!(
+                    "invalid layout: cell {}x{},{},{} doesn't fit its parent",
+                    child.width,
+                    child.height,
+                    child.x,
+                    child.y
+                ));
+            }
+            // Sizes come from hand-edited or imported text, so they can be
+            // anything up to u32::MAX.
+            let (at, size) = if horizontal { (&mut x, child.width) } else { (&mut y, child.height) };
+            *at = at
+                .checked_add(size)
+                .and_then(|v| v.checked_add(1))
+                .ok_or_else(|| eyre!("invalid layout: layout geometry overflows"))?;
+            child.check()?;
+        }
+
+        let end = if horizontal { x - 1 - self.x } else { y - 1 - self.y };
+        let size = if horizontal { self.width } else { self.height };
+        if end != size {
+            return Err(eyre!(
+                "invalid layout: cells add up to {} but their parent is {}",
+                end,
+                size
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_body(&self, out: &mut String) {
+        out.push_str(&format!("{}x{},{},{}", self.width, self.height, self.x, self.y));
+        let (children, open, close) = match &self.kind {
+            LayoutKind::Pane(Some(id)) => {
+                out.push_str(&format!(",{}", id));
+                return;
+            }
+            LayoutKind::Pane(None) => return,
+            LayoutKind::Horizontal(c) => (c, '{', '}'),
+            LayoutKind::Vertical(c) => (c, '[', ']'),
+        };
+        out.push(open);
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            child.write_body(out);
+        }
+        out.push(close);
+    }
+}
+
+/// The full layout string, with a freshly computed checksum.
+impl fmt::Display for LayoutNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut body = String::new();
+        self.write_body(&mut body);
+        write!(f, "{:04x},{}", checksum(&body), body)
+    }
+}
+
+fn shape_list(children: &[LayoutNode]) -> String {
+    children.iter().map(|c| c.shape()).collect::<Vec<_>>().join(" ")
+}
+
+/// tmux's 16-bit rotating checksum over the layout body.
+pub fn checksum(body: &str) -> u16 {
+    body.bytes().fold(0u16, |csum, b| {
+        let csum = (csum >> 1) | ((csum & 1) << 15);
+        csum.wrapping_add(b as u16)
+    })
+}
+
+/// Split off a leading `xxxx,` checksum. The body always starts with `WxH`,
+/// so four hex digits before the first comma can only be a checksum.
+fn split_checksum(layout: &str) -> Option<(u16, &str)> {
+    let (head, body) = layout.split_once(',')?;
+    if head.len() != 4 {
+        return None;
+    }
+    u16::from_str_radix(head, 16).ok().map(|csum| (csum, body))
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn node(&mut self) -> Result<LayoutNode> {
+        let width = self.number()?;
+        self.expect(b'x')?;
+        let height = self.number()?;
+        self.expect(b',')?;
+        let x = self.number()?;
+        self.expect(b',')?;
+        let y = self.number()?;
+
+        let kind = match self.peek() {
+            Some(b'{') => LayoutKind::Horizontal(self.children(b'}')?),
+            Some(b'[') => LayoutKind::Vertical(self.children(b']')?),
+            // A comma starts either this pane's id or the next sibling, which
+            // is told apart by the `x` in its `WxH`.
+            Some(b',') if !self.next_is_cell() => {
+                self.pos += 1;
+                LayoutKind::Pane(Some(self.number()?))
+            }
+            _ => LayoutKind::Pane(None),
+        };
+
+        Ok(LayoutNode {
+            width,
+            height,
+            x,
+            y,
+            kind,
+        })
+    }
+
+    fn children(&mut self, close: u8) -> Result<Vec<LayoutNode>> {
+        self.pos += 1;
+        let mut children = vec![self.node()?];
+        loop {
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    children.push(self.node()?);
+                }
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(children);
+                }
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close as char))),
+            }
+        }
+    }
+
+    fn next_is_cell(&self) -> bool {
+        let digits = self.input[self.pos + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.input.get(self.pos + 1 + digits) == Some(&b'x')
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error("expected a number"))
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn error(&self, msg: &str) -> color_eyre::eyre::Report {
+        eyre!("invalid layout: {} at offset {}", msg, self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN_VERTICAL: &str = "bf1f,120x30,0,0{80x30,0,0,2,39x30,81,0[39x14,81,0,3,39x15,81,15,4]}";
+
+    #[test]
+    fn parses_and_reserializes_tmux_layouts() {
+        for layout in [MAIN_VERTICAL, "b25d,80x24,0,0,0"] {
+            assert_eq!(LayoutNode::parse(layout).unwrap().to_string(), layout);
+        }
+
+        let node = LayoutNode::parse(MAIN_VERTICAL).unwrap();
+        assert_eq!(node.shape(), "{%2 [%3 %4]}");
+        let cells: Vec<_> = node.panes().iter().map(|p| (p.x, p.y, p.width, p.height)).collect();
+        assert_eq!(cells, [(0, 0, 80, 30), (81, 0, 39, 14), (81, 15, 39, 15)]);
+    }
+
+    #[test]
+    fn hand_edited_layouts_get_a_fresh_checksum() {
+        let edited = "0000,120x30,0,0{60x30,0,0,2,59x30,61,0,3}";
+        assert!(LayoutNode::parse(edited).is_err());
+
+        let node = LayoutNode::parse_unchecked(edited).unwrap();
+        assert_eq!(LayoutNode::parse(&node.to_string()).unwrap(), node);
+        assert!(LayoutNode::parse_unchecked("120x30,0,0{60x30,0,0,59x30,61,0}").is_ok());
+    }
+
+    #[test]
+    fn rejects_cells_that_do_not_tile() {
+        let err = LayoutNode::parse_unchecked("120x30,0,0{60x30,0,0,2,50x30,61,0,3}").unwrap_err();
+        assert!(err.to_string().contains("add up"), "{}", err);
+        assert!(LayoutNode::parse_unchecked("120x30,0,0{60x30,0,0,2").is_err());
+    }
+
+    #[test]
+    fn rejects_geometry_that_overflows() {
+        let err = LayoutNode::parse_unchecked("4294967295x1,0,0{4294967295x1,0,0,1,0x1,0,0,2}").unwrap_err();
+        assert!(err.to_string().contains("overflows"), "{}", err);
+    }
+}
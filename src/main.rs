@@ -1,14 +1,24 @@
+mod ansi;
 mod app;
+mod cli;
 mod event;
+mod export;
+mod fuzzy;
+mod import;
+mod layout;
 mod model;
 mod template;
 mod tmux;
 mod ui;
 
+use std::collections::BTreeMap;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
 use std::time::Duration;
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, eyre};
 use crossterm::event::KeyEventKind;
 use crossterm::execute;
 use crossterm::terminal::{
@@ -18,17 +28,83 @@ use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 
 use app::App;
+use cli::{Cli, Command};
 use event::{AppEvent, EventReader};
+use export::ExportFormat;
+use tmux::{Socket, TmuxBackend, TmuxClient};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    // Check tmux is available
-    if !tmux::is_tmux_running() {
-        eprintln!("Error: tmux server is not running.");
+    let cli = match Cli::parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    match &cli.command {
+        Some(Command::Import { files, force }) => {
+            let ok = import_files(files, *force);
+            std::process::exit(if ok { 0 } else { 1 });
+        }
+        Some(Command::CheckTemplates { targets }) => {
+            let ok = check_templates(targets);
+            std::process::exit(if ok { 0 } else { 1 });
+        }
+        Some(Command::Launch {
+            template,
+            session,
+            vars,
+            dry_run,
+        }) => {
+            if let Err(e) = launch(&cli.socket, template, session.as_deref(), vars, *dry_run, cli.hooks) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Export {
+            name,
+            session,
+            with_options,
+            format,
+            output,
+            vars,
+        }) => {
+            let session = session.then_some(*with_options);
+            if let Err(e) = export_template(&cli.socket, name, session, *format, output.as_deref(), vars) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // Check tmux is available. With --all-servers any running server will do
+    // as the primary one.
+    let socket = if cli.all_servers {
+        let requested = cli.socket.canonical();
+        std::iter::once(requested)
+            .chain(tmux::discover_sockets())
+            .find(tmux::is_tmux_running)
+    } else {
+        Some(cli.socket.clone()).filter(tmux::is_tmux_running)
+    };
+    let Some(socket) = socket else {
+        if cli.all_servers {
+            eprintln!("Error: no tmux server is running.");
+        } else {
+            eprintln!("Error: tmux server '{}' is not running.", cli.socket.label());
+        }
         eprintln!("Start tmux first, then run tmmx.");
         std::process::exit(1);
-    }
+    };
 
     // Setup terminal
     enable_raw_mode()?;
@@ -45,30 +121,240 @@ fn main() -> Result<()> {
         original_hook(panic_info);
     }));
 
-    let result = run_app(&mut terminal);
+    let result = run_app(&mut terminal, socket, cli.all_servers, cli.hooks, cli.history);
 
     // Restore terminal
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
 
     // Handle suspend (attach to tmux session from outside)
-    if let Ok(Some(target)) = &result {
-        let status = std::process::Command::new("tmux")
-            .args(["attach-session", "-t", target])
-            .status();
-        if let Err(e) = status {
-            eprintln!("Failed to attach to tmux: {}", e);
-        }
+    if let Ok(Some((socket, target))) = &result
+        && let Err(e) = tmux::attach_session(socket, target)
+    {
+        eprintln!("Failed to attach to tmux: {}", e);
     }
 
     result.map(|_| ())
 }
 
+/// Save each project file as a template, reporting per file. Returns whether
+/// every file was imported.
+fn import_files(files: &[PathBuf], force: bool) -> bool {
+    let mut ok = true;
+    for file in files {
+        let result = import::import_file(file).and_then(|imported| {
+            let name = &imported.template.template.name;
+            if !force && template::template_exists(name) {
+                return Err(eyre!(
+                    "Template '{}' already exists (use --force to overwrite)",
+                    name
+                ));
+            }
+            template::save_template(&imported.template)?;
+            Ok(imported)
+        });
+        match result {
+            Ok(imported) => {
+                println!("Imported '{}' from {}", imported.template.template.name, file.display());
+                for warning in &imported.warnings {
+                    println!("  warning: {}", warning);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", file.display(), e);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Print every problem in the given template files or names, or in all saved
+/// templates, one per line. Returns whether none were found.
+fn check_templates(targets: &[String]) -> bool {
+    let saved = template::load_template_files();
+    let mut files = Vec::new();
+    let mut ok = true;
+    if targets.is_empty() {
+        files = saved;
+    } else {
+        for target in targets {
+            let path = Path::new(target);
+            if path.is_file() {
+                // A project's own file is checked as the picker would see it.
+                let project = path.canonicalize().ok().and_then(|path| {
+                    let (root, paths) = template::find_project_templates(path.parent()?)?;
+                    paths.contains(&path).then_some(root)
+                });
+                let mut file = template::TemplateFile::read(path, project.as_deref());
+                file.resolve(&saved);
+                files.push(file);
+            } else if let Some(file) = saved.iter().find(|f| f.name() == *target) {
+                files.push(file.clone());
+            } else {
+                eprintln!("Error: Template '{}' not found", target);
+                ok = false;
+            }
+        }
+    }
+
+    for file in &files {
+        if file.problems.is_empty() {
+            println!("{}: ok", file.path.display());
+        }
+        for problem in &file.problems {
+            println!("{}", file.report(problem));
+        }
+    }
+    ok && files.iter().all(|f| !f.is_broken())
+}
+
+/// Start a session from a saved template, or with `dry_run` print the tmux
+/// commands that would do it. Without `hooks` the template's hooks are left
+/// out.
+fn launch(
+    socket: &Socket,
+    name: &str,
+    session: Option<&str>,
+    vars: &BTreeMap<String, String>,
+    dry_run: bool,
+    hooks: bool,
+) -> Result<()> {
+    let mut template = resolve(&find_template(name)?, vars)?;
+    if !hooks {
+        template = template.without_hooks();
+    }
+    let session = session.unwrap_or(&template.template.name);
+    let tmux = TmuxClient::new(socket.clone());
+    if dry_run {
+        let plan = template::plan_launch(&template, session)?;
+        for command in plan.commands() {
+            println!("{}", command);
+        }
+        return plan.check(&tmux);
+    }
+    for line in template::launch_template(&tmux, &template, session)? {
+        println!("{}", line);
+    }
+    println!("Session '{}' created from template '{}'", session, name);
+    Ok(())
+}
+
+/// The saved template called `name`. If its file doesn't parse, that is the
+/// error rather than "not found".
+fn find_template(name: &str) -> Result<template::SessionTemplate> {
+    let file = template::load_template_files()
+        .into_iter()
+        .find(|f| f.name() == name)
+        .ok_or_else(|| eyre!("Template '{}' not found", name))?;
+    match file.template {
+        Some(t) => Ok(t),
+        None => Err(eyre!("{}", file.report(&file.problems[0]))),
+    }
+}
+
+/// Fill in a template's variables from `--var` values. Variables the TUI
+/// would prompt for must be given.
+fn resolve(
+    template: &template::SessionTemplate,
+    vars: &BTreeMap<String, String>,
+) -> Result<template::SessionTemplate> {
+    if let Some(name) = template::unset_variables(template)
+        .into_iter()
+        .find(|n| !vars.contains_key(n))
+    {
+        return Err(eyre!("Template variable '{}' has no value (pass --var {}=...)", name, name));
+    }
+    template::resolve_variables(template, vars)
+}
+
+/// Print or write `name`, a saved template or with `session` a running
+/// session, in `format`. `session` says whether to keep the session's options
+/// and environment.
+fn export_template(
+    socket: &Socket,
+    name: &str,
+    session: Option<bool>,
+    format: ExportFormat,
+    output: Option<&Path>,
+    vars: &BTreeMap<String, String>,
+) -> Result<()> {
+    let template = if let Some(with_options) = session {
+        if !tmux::is_tmux_running(socket) {
+            return Err(eyre!("tmux server '{}' is not running", socket.label()));
+        }
+        template::capture_session_as_template(&TmuxClient::new(socket.clone()), name, with_options)?
+    } else {
+        resolve(&find_template(name)?, vars)?
+    };
+    let exported = export::export(&template, format)?;
+    // Stdout may be the export itself, so warnings go to stderr.
+    for warning in &exported.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    match output {
+        Some(path) => export::write_export(path, &exported.content, format),
+        None => {
+            print!("{}", exported.content);
+            Ok(())
+        }
+    }
+}
+
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-) -> Result<Option<String>> {
-    let mut app = App::new();
-    let mut events = EventReader::new(Duration::from_millis(250));
+    socket: Socket,
+    all_servers: bool,
+    hooks: bool,
+    history: usize,
+) -> Result<Option<(Socket, String)>> {
+    // Every server's control client feeds the same channel.
+    let (notify, notifications) = mpsc::channel();
+    let client = TmuxClient::new(socket);
+    client.subscribe(notify.clone());
+    let (background, background_events) = mpsc::channel();
+    let mut events = EventReader::new(Duration::from_millis(250), notifications, background_events);
+    let mut app = App::new(Box::new(client));
+    app.hooks = hooks;
+    app.set_preview_history(history);
+    app.set_preview_rows(ui::preview_rows(terminal.size()?.height));
+    let launched = background.clone();
+    app.enable_background_launch(Box::new(move |socket, template, session| {
+        let background = launched.clone();
+        thread::spawn(move || {
+            let tmux = TmuxClient::without_control(socket);
+            let progress = |text| {
+                let _ = background.send(AppEvent::LaunchProgress(text));
+            };
+            let result = template::launch_template_reporting(&tmux, &template, &session, &progress);
+            let _ = background.send(AppEvent::Launched(session, result.map_err(|e| e.to_string())));
+        });
+    }));
+    let hooked = background.clone();
+    app.enable_background_hooks(Box::new(move |hook, done| {
+        let background = hooked.clone();
+        thread::spawn(move || {
+            let _ = background.send(AppEvent::HookFinished(done, hook.run().map_err(|e| e.to_string())));
+        });
+    }));
+    app.enable_background_search(Box::new(move |search, panes| {
+        let background = background.clone();
+        thread::spawn(move || {
+            for (i, (socket, pane_id)) in panes.into_iter().enumerate() {
+                let text = TmuxClient::without_control(socket).capture_scrollback(&pane_id, None, false).ok();
+                if background.send(AppEvent::Scrollback(search, i, text)).is_err() {
+                    return;
+                }
+            }
+        });
+    }));
+    if all_servers {
+        app.enable_discovery(Box::new(move |socket| -> Box<dyn TmuxBackend> {
+            let client = TmuxClient::new(socket.clone());
+            client.subscribe(notify.clone());
+            Box::new(client)
+        }));
+    }
 
     loop {
         terminal.draw(|frame| ui::draw(frame, &mut app))?;
@@ -82,7 +368,15 @@ fn run_app(
             AppEvent::Tick => {
                 app.tick();
             }
-            AppEvent::Resize => {}
+            AppEvent::Resize => app.set_preview_rows(ui::preview_rows(terminal.size()?.height)),
+            AppEvent::SessionsChanged => app.on_sessions_changed(),
+            AppEvent::WindowsChanged => app.on_windows_changed(),
+            AppEvent::LayoutChanged(server, window_id) => app.on_layout_changed(&server, &window_id),
+            AppEvent::PaneOutput(server, pane_id) => app.on_pane_output(&server, &pane_id),
+            AppEvent::LaunchProgress(text) => app.on_launch_progress(text),
+            AppEvent::Launched(session, result) => app.on_launched(&session, result),
+            AppEvent::Scrollback(search, pane, text) => app.on_scrollback(search, pane, text),
+            AppEvent::HookFinished(done, result) => app.on_hook_finished(done, result),
         }
 
         if app.should_suspend {
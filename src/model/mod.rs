@@ -2,6 +2,6 @@ mod pane;
 mod session;
 mod window;
 
-pub use pane::TmuxPane;
+pub use pane::{PaneEntry, TmuxPane};
 pub use session::TmuxSession;
 pub use window::TmuxWindow;
@@ -14,3 +14,12 @@ pub struct TmuxPane {
     pub pid: u32,
     pub title: String,
 }
+
+/// A pane with the session and window it's in, as listed across a server.
+#[derive(Debug, Clone)]
+pub struct PaneEntry {
+    pub session: String,
+    pub window_index: u32,
+    pub window_name: String,
+    pub pane: TmuxPane,
+}
@@ -1,7 +1,12 @@
 use serde::Deserialize;
 
+use crate::tmux::Socket;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct TmuxSession {
+    /// Server the session lives on; set by the backend that listed it.
+    #[serde(skip)]
+    pub server: Socket,
     pub name: String,
     pub id: String,
     pub windows: u32,
@@ -1,10 +1,23 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use color_eyre::eyre::{Result, eyre};
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::tmux;
+use crate::layout::{LayoutNode, PRESETS};
+use crate::model::TmuxPane;
+use crate::tmux::{TmuxBackend, quote};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -18,26 +31,313 @@ pub enum SplitType {
 pub struct PaneTemplate {
     pub cwd: String,
     pub split: SplitType,
+    /// Typed into the pane's shell once the pane exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Further lines typed after `command`, each followed by Enter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub send_keys: Vec<String>,
+    /// When the pane's commands count as started. Later panes wait for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready: Option<Ready>,
+}
+
+/// What a pane waits for after its commands are typed, before the launch
+/// moves on. Every condition given must hold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ready {
+    /// A regex to find in a line of the pane's visible contents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// A TCP port accepting connections on localhost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// A file that exists, relative to the pane's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Seconds to wait before the launch fails; 0 checks just once.
+    #[serde(default = "Ready::default_timeout")]
+    pub timeout: u64,
+}
+
+impl Ready {
+    fn default_timeout() -> u64 {
+        30
+    }
+
+    /// Why the conditions can't be waited for, if they can't.
+    fn problem(&self) -> Option<String> {
+        if self.output.is_none() && self.port.is_none() && self.file.is_none() {
+            return Some("ready needs an output, port or file to wait for".to_string());
+        }
+        let output = self.output.as_deref().filter(|o| !o.contains("${"))?;
+        Regex::new(output).err().map(|e| format!("ready output is not a valid regex: {}", e))
+    }
+
+    /// The conditions that don't hold yet for pane `pane_id`, described.
+    fn unmet(&self, tmux: &dyn TmuxBackend, pane_id: &str) -> Result<Vec<String>> {
+        let mut unmet = Vec::new();
+        if let Some(output) = &self.output {
+            let regex = Regex::new(output)?;
+            if !tmux.capture_pane(pane_id)?.lines().any(|line| regex.is_match(line)) {
+                unmet.push(format!("output matching '{}'", output));
+            }
+        }
+        if let Some(port) = self.port
+            && !port_open(port)
+        {
+            unmet.push(format!("port {}", port));
+        }
+        if let Some(file) = &self.file
+            && !Path::new(file).exists()
+        {
+            unmet.push(format!("file {}", file));
+        }
+        Ok(unmet)
+    }
+}
+
+/// Whether something accepts connections on `port` on localhost.
+fn port_open(port: u16) -> bool {
+    ("localhost", port).to_socket_addrs().is_ok_and(|mut addrs| {
+        addrs.any(|addr| TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok())
+    })
+}
+
+/// Poll `ready` for the pane until it holds, failing once its timeout has
+/// passed. `waiting` is told the seconds left before each poll.
+fn wait_ready(tmux: &dyn TmuxBackend, pane_id: &str, ready: &Ready, waiting: &dyn Fn(u64)) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(ready.timeout);
+    loop {
+        waiting(deadline.saturating_duration_since(Instant::now()).as_secs());
+        let unmet = ready.unmet(tmux, pane_id)?;
+        if unmet.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            let mut message = format!("Not ready after {}s: no {}", ready.timeout, unmet.join(", no "));
+            let contents = tmux.capture_pane(pane_id).unwrap_or_default();
+            if let Some(last) = contents.lines().map(str::trim).rfind(|l| !l.is_empty()) {
+                message.push_str(&format!("; the pane last showed '{}'", last));
+            }
+            return Err(eyre!(message));
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowTemplate {
+    /// File holding a window fragment to start from, relative to this file.
+    /// Any field set here overrides the fragment's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include: Option<String>,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub cwd: String,
+    /// tmux `window_layout` string. When set, it decides how many panes the
+    /// window gets and is applied with `select-layout` once they exist, so
+    /// sizes come back exactly; `split` only decides how panes are first
+    /// created. The checksum is recomputed, so the string may be hand-edited.
+    /// One of tmux's named layouts, such as `main-vertical`, also works.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Run in every pane of the window before the pane's own command, e.g. to
+    /// activate an environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub send_keys: Vec<String>,
+    /// Window options, such as `synchronize-panes` or `remain-on-exit`, set
+    /// before the panes are split.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "option_values")]
+    pub options: BTreeMap<String, String>,
+    #[serde(default)]
     pub panes: Vec<PaneTemplate>,
 }
 
+/// Option values as tmux takes them. Numbers may be written bare, and
+/// booleans become `on` and `off`.
+fn option_values<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<BTreeMap<String, String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Text(String),
+        Number(i64),
+        Flag(bool),
+    }
+    let values = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::Text(text) => text,
+                Value::Number(n) => n.to_string(),
+                Value::Flag(flag) => if flag { "on" } else { "off" }.to_string(),
+            };
+            (name, value)
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionTemplate {
     pub template: TemplateMeta,
+    /// Session options set with `set-option`, such as `status-style`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "option_values")]
+    pub options: BTreeMap<String, String>,
+    /// Variables set in the session's environment, seen by every pane.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    #[serde(default)]
     pub windows: Vec<WindowTemplate>,
 }
 
+/// Shell commands run around the session's life, in the first window's
+/// directory and with the template's environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hooks {
+    /// Run before the session is created; the launch stops if it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_create: Option<String>,
+    /// Run whenever tmmx switches or attaches to the session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_attach: Option<String>,
+    /// Run after the session is killed from tmmx.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_stop: Option<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_create.is_none() && self.on_attach.is_none() && self.on_stop.is_none()
+    }
+}
+
+/// Session options `launch_template` records the `on_attach` and `on_stop`
+/// hooks in, so they can run long after the template was launched.
+const HOOK_OPTION_PREFIX: &str = "@tmmx-";
+/// How long a hook may run before it is killed and counts as failed.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often a running hook is checked on.
+const HOOK_POLL: Duration = Duration::from_millis(20);
+
+/// A hook command, ready to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    /// `on_create`, `on_attach` or `on_stop`.
+    pub name: &'static str,
+    pub command: String,
+    pub cwd: String,
+    pub environment: BTreeMap<String, String>,
+}
+
+impl Hook {
+    /// Run the command with `sh -c` and wait for it, for up to
+    /// `HOOK_TIMEOUT`. Returns the last line it printed, if any, for the
+    /// status bar; a failure carries the last line of its error output.
+    pub fn run(&self) -> Result<Option<String>> {
+        self.run_within(HOOK_TIMEOUT)
+    }
+
+    fn run_within(&self, timeout: Duration) -> Result<Option<String>> {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.command)
+            .envs(&self.environment)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Its own process group, so a timeout also stops what it started.
+            .process_group(0);
+        if Path::new(&self.cwd).is_dir() {
+            command.current_dir(&self.cwd);
+        }
+        let mut child = command.spawn().map_err(|e| eyre!("Can't run the {} hook: {}", self.name, e))?;
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        // Done once the hook has exited and nothing it left running holds
+        // its output open.
+        let started = Instant::now();
+        let mut status = None;
+        while status.is_none() || !stdout.is_finished() || !stderr.is_finished() {
+            if status.is_none() {
+                status = child.try_wait()?;
+            }
+            if started.elapsed() >= timeout {
+                // SAFETY: kill(2) with the group the hook leads; it has no
+                // memory-safety preconditions.
+                unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                let _ = child.wait();
+                return Err(eyre!("{} hook timed out after {:?}", self.name, timeout));
+            }
+            thread::sleep(HOOK_POLL);
+        }
+        let status = status.expect("loop ends once the hook has exited");
+        let (stdout, stderr) = (stdout.join().unwrap_or_default(), stderr.join().unwrap_or_default());
+
+        let last_line = |bytes: &[u8]| {
+            String::from_utf8_lossy(bytes)
+                .lines()
+                .map(str::trim)
+                .rfind(|l| !l.is_empty())
+                .map(str::to_string)
+        };
+        if status.success() {
+            Ok(last_line(&stdout).or_else(|| last_line(&stderr)).map(|line| format!("{}: {}", self.name, line)))
+        } else {
+            let detail = last_line(&stderr).or_else(|| last_line(&stdout)).unwrap_or_default();
+            Err(eyre!("{} hook failed ({}): {}", self.name, status, detail))
+        }
+    }
+}
+
+/// Read `pipe` to the end on its own thread, so it can't fill up and stall
+/// the process writing to it.
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// The `on_attach` or `on_stop` hook recorded on a running session, if it was
+/// launched from a template that has one.
+pub fn session_hook(tmux: &dyn TmuxBackend, session_name: &str, name: &'static str) -> Result<Option<Hook>> {
+    let options = tmux.show_options(session_name, None)?;
+    let Some(command) = options.get(&format!("{}{}", HOOK_OPTION_PREFIX, name)) else {
+        return Ok(None);
+    };
+    Ok(Some(Hook {
+        name,
+        // Stored as a JSON string, though one set by hand is taken as it is.
+        command: serde_json::from_str(command).unwrap_or_else(|_| command.clone()),
+        cwd: options.get(&format!("{}hook_cwd", HOOK_OPTION_PREFIX)).cloned().unwrap_or_default(),
+        environment: tmux.show_environment(session_name)?,
+    }))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateMeta {
     pub name: String,
     #[serde(default)]
     pub description: String,
+    /// Name of a template to build on. Its variables and windows come first;
+    /// a window of the same name here overrides the fields it sets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Defaults for `${name}` placeholders. An empty default means the value
+    /// is asked for at launch.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
 }
 
 pub fn template_dir() -> PathBuf {
@@ -47,26 +347,347 @@ pub fn template_dir() -> PathBuf {
         .join("templates")
 }
 
-pub fn load_all_templates() -> Vec<SessionTemplate> {
-    let dir = template_dir();
-    if !dir.exists() {
-        return Vec::new();
-    }
-    let mut templates = Vec::new();
-    if let Ok(entries) = fs::read_dir(&dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().is_some_and(|e| e == "toml") {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    if let Ok(t) = toml::from_str::<SessionTemplate>(&content) {
-                        templates.push(t);
+/// Something wrong with a template file. Parse errors and unknown keys come
+/// with a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateProblem {
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl TemplateProblem {
+    fn new(message: String) -> Self {
+        Self { position: None, message }
+    }
+}
+
+impl fmt::Display for TemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// A template file as found on disk. `template` is `None` when the file
+/// doesn't parse; one that does may still have problems.
+#[derive(Debug, Clone)]
+pub struct TemplateFile {
+    pub path: PathBuf,
+    /// For a template shipped with a project, the project's directory.
+    pub project: Option<PathBuf>,
+    pub template: Option<SessionTemplate>,
+    pub problems: Vec<TemplateProblem>,
+}
+
+impl TemplateFile {
+    /// Read the template at `path`, with the windows it includes merged in.
+    /// A project template's relative directories are taken from `project`.
+    /// `extends` and the checks wait for `resolve`.
+    pub fn read(path: &Path, project: Option<&Path>) -> Self {
+        let mut file = Self {
+            path: path.to_path_buf(),
+            project: project.map(Path::to_path_buf),
+            template: None,
+            problems: Vec::new(),
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                file.problems.push(TemplateProblem::new(format!("Can't read the file: {}", e)));
+                return file;
+            }
+        };
+        let (template, problems) = parse_toml::<SessionTemplate>(&content);
+        file.problems = problems;
+        let Some(mut template) = template else {
+            return file;
+        };
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for win in &mut template.windows {
+            include_window(win, dir, &mut Vec::new(), &mut file.problems);
+        }
+        if let Some(root) = project {
+            template.rebase(root);
+        }
+        file.template = Some(template);
+        file
+    }
+
+    /// Merge in the templates this one `extends`, looked up by name in
+    /// `library`, then check the result.
+    pub fn resolve(&mut self, library: &[TemplateFile]) {
+        let Some(template) = &mut self.template else {
+            return;
+        };
+        match extend(template.clone(), &self.path, library, &mut Vec::new()) {
+            Ok(extended) => *template = extended,
+            Err(e) => self.problems.push(TemplateProblem::new(e.to_string())),
+        }
+        self.problems.extend(template_problems(template));
+    }
+
+    /// The template's name, or for a file that doesn't parse, the file's.
+    pub fn name(&self) -> String {
+        match &self.template {
+            Some(t) => t.template.name.clone(),
+            None => self
+                .path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_broken(&self) -> bool {
+        !self.problems.is_empty()
+    }
+
+    /// `problem` prefixed with the file, as compilers print them.
+    pub fn report(&self, problem: &TemplateProblem) -> String {
+        match problem.position {
+            Some(_) => format!("{}:{}", self.path.display(), problem),
+            None => format!("{}: {}", self.path.display(), problem),
+        }
+    }
+}
+
+/// The templates of the project around the working directory, then every
+/// `.toml` file in `template_dir()`, broken ones included, each resolved and
+/// checked. Sorted by name, with a project template ahead of a saved one of
+/// the same name.
+pub fn load_template_files() -> Vec<TemplateFile> {
+    let project = std::env::current_dir().ok().and_then(|dir| find_project_templates(&dir));
+    let project_files = project
+        .iter()
+        .flat_map(|(root, paths)| paths.iter().map(|path| TemplateFile::read(path, Some(root))));
+    let mut files: Vec<TemplateFile> = project_files
+        .chain(toml_files(&template_dir()).iter().map(|path| TemplateFile::read(path, None)))
+        .collect();
+    files.sort_by_key(|f| (f.name(), f.project.is_none()));
+    let library = files.clone();
+    for file in &mut files {
+        file.resolve(&library);
+    }
+    files
+}
+
+/// The nearest directory at or above `dir` that has a `.tmmx.toml` or a
+/// `.tmmx/templates` directory, the way git looks for `.git`, with the
+/// template files found there.
+pub fn find_project_templates(dir: &Path) -> Option<(PathBuf, Vec<PathBuf>)> {
+    dir.ancestors().find_map(|root| {
+        let single = root.join(".tmmx.toml");
+        let mut paths = toml_files(&root.join(".tmmx").join("templates"));
+        if single.is_file() {
+            paths.insert(0, single);
+        }
+        (!paths.is_empty()).then(|| (root.to_path_buf(), paths))
+    })
+}
+
+fn toml_files(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// `template` on top of the templates it extends, nearest first. A template
+/// may extend a namesake in another file, such as a project template
+/// building on a saved one. `seen` holds the files on the way, to catch loops.
+fn extend(
+    template: SessionTemplate,
+    path: &Path,
+    library: &[TemplateFile],
+    seen: &mut Vec<PathBuf>,
+) -> Result<SessionTemplate> {
+    let Some(name) = template.template.extends.clone() else {
+        return Ok(template);
+    };
+    seen.push(path.to_path_buf());
+    let base = library
+        .iter()
+        .find(|f| f.path != path && f.name() == name)
+        .ok_or_else(|| eyre!("Template '{}' to extend was not found", name))?;
+    if seen.contains(&base.path) {
+        return Err(eyre!("Template '{}' ends up extending itself", name));
+    }
+    let Some(base_template) = base.template.clone() else {
+        return Err(eyre!("Template '{}' to extend doesn't parse", name));
+    };
+    let base_template = extend(base_template, &base.path, library, seen)?;
+    Ok(template.over(base_template))
+}
+
+/// Merge the window fragment `win` includes underneath it, and any fragment
+/// that one includes in turn. Paths are relative to `dir`, the directory of
+/// the including file.
+fn include_window(
+    win: &mut WindowTemplate,
+    dir: &Path,
+    seen: &mut Vec<PathBuf>,
+    problems: &mut Vec<TemplateProblem>,
+) {
+    let Some(include) = win.include.take() else {
+        return;
+    };
+    let path = dir.join(&include);
+    let mut problem = |message: String| {
+        problems.push(TemplateProblem::new(format!("Include '{}': {}", include, message)));
+    };
+    if seen.contains(&path) {
+        return problem("includes itself".to_string());
+    }
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => return problem(format!("can't read {}: {}", path.display(), e)),
+    };
+    let (fragment, fragment_problems) = parse_toml::<WindowTemplate>(&content);
+    for p in fragment_problems {
+        problem(p.to_string());
+    }
+    let Some(mut fragment) = fragment else {
+        return;
+    };
+    seen.push(path.clone());
+    include_window(&mut fragment, path.parent().unwrap_or(dir), seen, problems);
+    seen.pop();
+    *win = win.clone().over(fragment);
+}
+
+/// Deserialize `content`, noting keys that `T` doesn't have. A parse error is
+/// the only problem when there is one.
+fn parse_toml<T: DeserializeOwned>(content: &str) -> (Option<T>, Vec<TemplateProblem>) {
+    let mut unknown = Vec::new();
+    let parsed: std::result::Result<T, toml::de::Error> =
+        serde_ignored::deserialize(toml::Deserializer::new(content), |path| unknown.push(path.to_string()));
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let problem = TemplateProblem {
+                position: e.span().map(|span| line_column(content, span.start)),
+                message: e.message().trim_end().to_string(),
+            };
+            return (None, vec![problem]);
+        }
+    };
+
+    let mut problems = Vec::new();
+    // The nth unknown key of a name is taken to be its nth appearance.
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for path in unknown {
+        let key = path.rsplit('.').next().unwrap_or(&path).to_string();
+        let nth = seen.entry(key.clone()).or_default();
+        problems.push(TemplateProblem {
+            position: find_key(content, &key, *nth),
+            message: format!("Unknown key '{}'", path),
+        });
+        *nth += 1;
+    }
+    (Some(parsed), problems)
+}
+
+/// What would go wrong with a resolved template: no windows, windows without
+/// a name or sharing one, panes whose `full` split is out of place, layouts
+/// tmux won't take and directories that don't exist. Directories are only
+/// checked once every variable has a value.
+pub fn template_problems(template: &SessionTemplate) -> Vec<TemplateProblem> {
+    let mut problems = Vec::new();
+    if template.windows.is_empty() {
+        problems.push(TemplateProblem::new("Template has no windows".to_string()));
+    }
+    let mut names = Vec::new();
+    for (i, win) in template.windows.iter().enumerate() {
+        if win.name.is_empty() {
+            problems.push(TemplateProblem::new(format!("Window {} has no name", i)));
+        } else if names.contains(&&win.name) {
+            let problem = TemplateProblem::new(format!("Window name '{}' is used more than once", win.name));
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+        names.push(&win.name);
+        for (i, pane) in win.panes.iter().enumerate() {
+            match (i, &pane.split) {
+                (0, SplitType::Full) | (1.., SplitType::Horizontal | SplitType::Vertical) => {}
+                (0, _) => problems.push(TemplateProblem::new(format!(
+                    "Window '{}': the first pane must have split = \"full\"",
+                    win.name
+                ))),
+                (_, SplitType::Full) => problems.push(TemplateProblem::new(format!(
+                    "Window '{}', pane {}: only the first pane can have split = \"full\"",
+                    win.name, i
+                ))),
+            }
+            if let Some(problem) = pane.ready.as_ref().and_then(Ready::problem) {
+                problems.push(TemplateProblem::new(format!("Window '{}', pane {}: {}", win.name, i, problem)));
+            }
+        }
+        if let Err(e) = win.pane_plan() {
+            problems.push(TemplateProblem::new(format!("Window '{}': {}", win.name, e)));
+        }
+    }
+
+    if unset_variables(template).is_empty() {
+        match resolve_variables(template, &BTreeMap::new()) {
+            Ok(resolved) => {
+                let mut missing = Vec::new();
+                for win in &resolved.windows {
+                    let cwds = std::iter::once((None, &win.cwd))
+                        .chain(win.panes.iter().enumerate().map(|(i, p)| (Some(i), &p.cwd)));
+                    for (pane, cwd) in cwds {
+                        if cwd.is_empty() || missing.contains(&cwd) || Path::new(cwd).is_dir() {
+                            continue;
+                        }
+                        let at = match pane {
+                            Some(i) => format!("Window '{}', pane {}", win.name, i),
+                            None => format!("Window '{}'", win.name),
+                        };
+                        problems.push(TemplateProblem::new(format!("{}: directory {} does not exist", at, cwd)));
+                        missing.push(cwd);
                     }
                 }
             }
+            Err(e) => problems.push(TemplateProblem::new(e.to_string())),
+        }
+    }
+    problems
+}
+
+/// 1-based line and column of byte `offset`.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Where `key` is assigned for the `nth` time, as a bare key followed by `=`
+/// or as a table header.
+fn find_key(content: &str, key: &str, nth: usize) -> Option<(usize, usize)> {
+    let mut found = 0;
+    let mut offset = 0;
+    while let Some(i) = content[offset..].find(key) {
+        let start = offset + i;
+        let end = start + key.len();
+        offset = end;
+        let before = content[..start].chars().next_back();
+        let after = content[end..].trim_start_matches([' ', '\t']).chars().next();
+        let bare = !before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if bare && matches!(after, Some('=' | ']' | '.')) {
+            if found == nth {
+                return Some(line_column(content, start));
+            }
+            found += 1;
         }
     }
-    templates.sort_by(|a, b| a.template.name.cmp(&b.template.name));
-    templates
+    None
 }
 
 pub fn save_template(template: &SessionTemplate) -> Result<()> {
@@ -92,12 +713,25 @@ pub fn template_exists(name: &str) -> bool {
     template_dir().join(format!("{}.toml", name)).exists()
 }
 
-pub fn capture_session_as_template(session_name: &str) -> Result<SessionTemplate> {
-    let windows = tmux::list_windows(session_name)?;
+/// Record a running session as a template. With `settings`, the options and
+/// environment set on the session and its windows are kept as well.
+pub fn capture_session_as_template(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    settings: bool,
+) -> Result<SessionTemplate> {
+    let windows = tmux.list_windows(session_name)?;
+    // Captured values are taken literally, not as placeholders.
+    let literal = |values: BTreeMap<String, String>| {
+        values
+            .into_iter()
+            .map(|(name, value)| (name, value.replace("${", "$${")))
+            .collect::<BTreeMap<_, _>>()
+    };
     let mut window_templates = Vec::new();
 
     for win in &windows {
-        let panes = tmux::list_panes(session_name, win.index)?;
+        let panes = tmux.list_panes(session_name, win.index)?;
         let mut pane_templates = Vec::new();
 
         for (i, pane) in panes.iter().enumerate() {
@@ -111,66 +745,1569 @@ pub fn capture_session_as_template(session_name: &str) -> Result<SessionTemplate
             pane_templates.push(PaneTemplate {
                 cwd: pane.cwd.clone(),
                 split,
+                command: suggested_command(&pane.command),
+                send_keys: Vec::new(),
+                ready: None,
             });
         }
 
         let cwd = panes.first().map(|p| p.cwd.clone()).unwrap_or_default();
         window_templates.push(WindowTemplate {
+            include: None,
             name: win.name.clone(),
             cwd,
+            layout: Some(win.layout.clone()).filter(|l| !l.is_empty()),
+            command: None,
+            send_keys: Vec::new(),
+            options: if settings {
+                // Naming a window turns this off, and launch names every window.
+                let mut options = tmux.show_options(session_name, Some(win.index))?;
+                if options.get("automatic-rename").is_some_and(|v| v == "off") {
+                    options.remove("automatic-rename");
+                }
+                literal(options)
+            } else {
+                BTreeMap::new()
+            },
             panes: pane_templates,
         });
     }
 
+    let (options, environment) = if settings {
+        // Recorded hooks are tmmx's own bookkeeping.
+        let mut options = tmux.show_options(session_name, None)?;
+        options.retain(|name, _| !name.starts_with(HOOK_OPTION_PREFIX));
+        (literal(options), literal(tmux.show_environment(session_name)?))
+    } else {
+        Default::default()
+    };
     Ok(SessionTemplate {
         template: TemplateMeta {
             name: session_name.to_string(),
             description: String::new(),
+            extends: None,
+            variables: BTreeMap::new(),
         },
+        options,
+        environment,
+        hooks: Hooks::default(),
         windows: window_templates,
     })
 }
 
-pub fn launch_template(template: &SessionTemplate, session_name: &str) -> Result<()> {
-    if template.windows.is_empty() {
-        return Err(eyre!("Template has no windows"));
+/// A pane's `pane_current_command` as a startup command, unless it is just
+/// the shell every new pane starts anyway. Arguments aren't known, so this is
+/// a suggestion to edit rather than an exact record.
+fn suggested_command(current: &str) -> Option<String> {
+    const SHELLS: [&str; 10] = ["bash", "zsh", "fish", "sh", "dash", "ksh", "tcsh", "csh", "nu", "pwsh"];
+    let name = current.trim_start_matches('-');
+    (!name.is_empty() && !SHELLS.contains(&name)).then(|| name.to_string())
+}
+
+/// Variables that need a value from the user before launch: placeholders
+/// with no default (declared empty or not declared at all), in the order
+/// they first appear.
+pub fn unset_variables(template: &SessionTemplate) -> Vec<String> {
+    let mut names = Vec::new();
+    for text in template.clone().texts_mut() {
+        let _ = expand(text, |name| {
+            if !name.starts_with("env:") && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+            Ok(String::new())
+        });
+    }
+    for name in template.template.variables.keys() {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
     }
+    names.retain(|n| template.template.variables.get(n).is_none_or(|v| v.is_empty()));
+    names
+}
 
-    let first_win = &template.windows[0];
-    // Create session with first window
-    tmux::new_session_with_cwd(session_name, &first_win.cwd)?;
-    tmux::rename_window(session_name, 0, &first_win.name)?;
+/// A copy of `template` with every `${name}` filled from `values`, falling
+/// back to the declared defaults, and every `${env:NAME}` from the
+/// environment. `$${` stands for a literal `${`. Defaults may use
+/// `${env:NAME}` themselves.
+pub fn resolve_variables(template: &SessionTemplate, values: &BTreeMap<String, String>) -> Result<SessionTemplate> {
+    let mut known = BTreeMap::new();
+    for (name, default) in &template.template.variables {
+        let default = expand(default, |placeholder| match placeholder.strip_prefix("env:") {
+            Some(var) => env_var(var),
+            None => Err(eyre!("Default of '{}' can only use ${{env:...}}", name)),
+        })?;
+        known.insert(name.clone(), default);
+    }
+    known.extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
 
-    // Create additional panes in first window
-    for pane in first_win.panes.iter().skip(1) {
-        match pane.split {
-            SplitType::Horizontal => {
-                tmux::split_window_in_dir(session_name, 0, "-v", &pane.cwd)?;
+    let mut resolved = template.clone();
+    for text in resolved.texts_mut() {
+        *text = expand(text, |name| match name.strip_prefix("env:") {
+            Some(var) => env_var(var),
+            None => known
+                .get(name)
+                .cloned()
+                .ok_or_else(|| eyre!("Template variable '{}' has no value", name)),
+        })?;
+    }
+    Ok(resolved)
+}
+
+impl SessionTemplate {
+    /// How many options and environment variables the template sets, on the
+    /// session and its windows together.
+    pub fn settings_count(&self) -> usize {
+        self.options.len() + self.environment.len() + self.windows.iter().map(|w| w.options.len()).sum::<usize>()
+    }
+
+    /// The template without any options or environment variables.
+    pub fn without_settings(mut self) -> SessionTemplate {
+        self.options.clear();
+        self.environment.clear();
+        for win in &mut self.windows {
+            win.options.clear();
+        }
+        self
+    }
+
+    /// `self` on top of `base`: its own name, its description if it has one,
+    /// the base's variables with its own added or overriding, and the base's
+    /// windows merged with its own by name, new ones last.
+    fn over(self, base: SessionTemplate) -> SessionTemplate {
+        let mut variables = base.template.variables;
+        variables.extend(self.template.variables);
+        let mut options = base.options;
+        options.extend(self.options);
+        let mut environment = base.environment;
+        environment.extend(self.environment);
+        let hooks = Hooks {
+            on_create: self.hooks.on_create.or(base.hooks.on_create),
+            on_attach: self.hooks.on_attach.or(base.hooks.on_attach),
+            on_stop: self.hooks.on_stop.or(base.hooks.on_stop),
+        };
+        let mut windows = base.windows;
+        for win in self.windows {
+            match windows.iter_mut().find(|w| w.name == win.name) {
+                Some(w) => *w = win.over(w.clone()),
+                None => windows.push(win),
             }
-            SplitType::Vertical => {
-                tmux::split_window_in_dir(session_name, 0, "-h", &pane.cwd)?;
+        }
+        SessionTemplate {
+            template: TemplateMeta {
+                name: self.template.name,
+                description: if self.template.description.is_empty() {
+                    base.template.description
+                } else {
+                    self.template.description
+                },
+                extends: None,
+                variables,
+            },
+            options,
+            environment,
+            hooks,
+            windows,
+        }
+    }
+
+    /// The hook called `name`, if the template has one, to run in the first
+    /// window's directory.
+    pub fn hook(&self, name: &'static str) -> Option<Hook> {
+        let command = match name {
+            "on_create" => &self.hooks.on_create,
+            "on_attach" => &self.hooks.on_attach,
+            "on_stop" => &self.hooks.on_stop,
+            _ => return None,
+        };
+        Some(Hook {
+            name,
+            command: command.clone()?,
+            cwd: self.windows.first().map(|w| w.cwd.clone()).unwrap_or_default(),
+            environment: self.environment.clone(),
+        })
+    }
+
+    /// The template without hooks, for launching with them skipped.
+    pub fn without_hooks(mut self) -> SessionTemplate {
+        self.hooks = Hooks::default();
+        self
+    }
+
+    /// Take relative and empty working directories from `root`. Ones that
+    /// start with a placeholder are left alone.
+    fn rebase(&mut self, root: &Path) {
+        let rebase = |cwd: &mut String| {
+            if cwd.is_empty() || cwd == "." {
+                *cwd = root.display().to_string();
+            } else if Path::new(cwd.as_str()).is_relative() && !cwd.starts_with("${") {
+                *cwd = root.join(cwd.as_str()).display().to_string();
+            }
+        };
+        for win in &mut self.windows {
+            rebase(&mut win.cwd);
+            for pane in &mut win.panes {
+                rebase(&mut pane.cwd);
             }
-            SplitType::Full => {}
         }
     }
 
-    // Create remaining windows
-    for (wi, win) in template.windows.iter().enumerate().skip(1) {
-        tmux::new_window_with_cwd(session_name, &win.name, &win.cwd)?;
-        let win_idx = wi as u32;
+    /// Every string that may hold placeholders: names, directories, commands,
+    /// hooks, and option and environment values.
+    fn texts_mut(&mut self) -> Vec<&mut String> {
+        let mut texts = vec![&mut self.template.name];
+        texts.extend(self.options.values_mut());
+        texts.extend(self.environment.values_mut());
+        let hooks = &mut self.hooks;
+        texts.extend([&mut hooks.on_create, &mut hooks.on_attach, &mut hooks.on_stop].into_iter().flatten());
+        for win in &mut self.windows {
+            texts.extend([&mut win.name, &mut win.cwd]);
+            texts.extend(win.command.as_mut());
+            texts.extend(win.send_keys.iter_mut());
+            texts.extend(win.options.values_mut());
+            for pane in &mut win.panes {
+                texts.push(&mut pane.cwd);
+                texts.extend(pane.command.as_mut());
+                texts.extend(pane.send_keys.iter_mut());
+                if let Some(ready) = &mut pane.ready {
+                    texts.extend(ready.output.as_mut().into_iter().chain(ready.file.as_mut()));
+                }
+            }
+        }
+        texts
+    }
+}
+
+fn env_var(var: &str) -> Result<String> {
+    std::env::var(var).map_err(|_| eyre!("Environment variable '{}' is not set", var))
+}
+
+/// Replace each `${...}` in `text` with `placeholder(name)`.
+fn expand(text: &str, mut placeholder: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find("${") {
+        if rest[..i].ends_with('$') {
+            out.push_str(&rest[..i - 1]);
+            out.push_str("${");
+            rest = &rest[i + 2..];
+            continue;
+        }
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| eyre!("Unclosed '${{' in \"{}\"", text))?;
+        out.push_str(&placeholder(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// One tmux operation of a launch. Windows are addressed by index, panes by
+/// their position in the window once all of its panes exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchStep {
+    /// The `on_create` hook, run before tmux is touched.
+    RunHook(Hook),
+    NewSession { cwd: String, environment: BTreeMap<String, String> },
+    /// A session option, or with `window` a window option.
+    SetOption { window: Option<u32>, name: String, value: String },
+    /// Drop a session option, so the global one applies again.
+    UnsetOption { name: String },
+    /// Renumber the windows from the session's `base-index`, once they exist.
+    RenumberWindows,
+    RenameWindow { window: u32, name: String },
+    NewWindow { window: u32, name: String, cwd: String },
+    SplitWindow { window: u32, direction: &'static str, cwd: String },
+    SelectLayout { window: u32, layout: String },
+    SendKeys { window: u32, pane: usize, line: String },
+    /// Hold the launch until the pane is ready. A relative `file` has been
+    /// taken from the pane's directory.
+    WaitReady { window: u32, pane: usize, ready: Ready },
+}
+
+/// Everything `launch_template` will do, in order, without touching tmux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchPlan {
+    pub session: String,
+    pub steps: Vec<LaunchStep>,
+}
+
+impl LaunchPlan {
+    /// Each step as the tmux command it amounts to.
+    pub fn commands(&self) -> Vec<String> {
+        let session = quote(&self.session);
+        let target = |window: &u32| quote(&format!("{}:{}", self.session, window));
+        self.steps
+            .iter()
+            .map(|step| match step {
+                LaunchStep::RunHook(hook) => format!("{} hook: sh -c {}", hook.name, quote(&hook.command)),
+                LaunchStep::NewSession { cwd, environment } => {
+                    let mut command = format!("new-session -d -s {} -c {}", session, quote(cwd));
+                    for (name, value) in environment {
+                        command.push_str(&format!(" -e {}", quote(&format!("{}={}", name, value))));
+                    }
+                    command
+                }
+                LaunchStep::SetOption { window: None, name, value } => {
+                    format!("set-option -t {} {} {}", session, quote(name), quote(value))
+                }
+                LaunchStep::SetOption { window: Some(window), name, value } => {
+                    format!("set-option -w -t {} {} {}", target(window), quote(name), quote(value))
+                }
+                LaunchStep::UnsetOption { name } => format!("set-option -u -t {} {}", session, quote(name)),
+                LaunchStep::RenumberWindows => format!("move-window -r -t {}", session),
+                LaunchStep::RenameWindow { window, name } => {
+                    format!("rename-window -t {} {}", target(window), quote(name))
+                }
+                LaunchStep::NewWindow { name, cwd, .. } => {
+                    format!("new-window -d -t {} -n {} -c {}", session, quote(name), quote(cwd))
+                }
+                LaunchStep::SplitWindow { window, direction, cwd } => {
+                    format!("split-window {} -t {} -c {}", direction, target(window), quote(cwd))
+                }
+                LaunchStep::SelectLayout { window, layout } => {
+                    format!("select-layout -t {} {}", target(window), quote(layout))
+                }
+                LaunchStep::SendKeys { window, pane, line } => {
+                    let pane = quote(&format!("{}:{}.{}", self.session, window, pane));
+                    format!("send-keys -t {} -l {} ; send-keys -t {} Enter", pane, quote(line), pane)
+                }
+                LaunchStep::WaitReady { window, pane, ready } => {
+                    let pane = quote(&format!("{}:{}.{}", self.session, window, pane));
+                    let mut conditions = Vec::new();
+                    conditions.extend(ready.output.as_ref().map(|o| format!("output matching {} in {}", quote(o), pane)));
+                    conditions.extend(ready.port.map(|port| format!("port {}", port)));
+                    conditions.extend(ready.file.as_ref().map(|file| format!("file {}", quote(file))));
+                    format!("wait up to {}s for {}", ready.timeout, conditions.join(" and "))
+                }
+            })
+            .collect()
+    }
 
-        for pane in win.panes.iter().skip(1) {
-            match pane.split {
-                SplitType::Horizontal => {
-                    tmux::split_window_in_dir(session_name, win_idx, "-v", &pane.cwd)?;
+    /// What would make the launch go wrong on `tmux`: a session name tmux
+    /// would rewrite, directories that don't exist and commands that can't be
+    /// typed as one line. Programs aren't looked up, since the shell may
+    /// provide them as aliases or functions.
+    pub fn problems(&self, tmux: &dyn TmuxBackend) -> Vec<String> {
+        let mut problems = Vec::new();
+        // Each missing directory is reported once, at its first use.
+        let mut missing = Vec::new();
+        if self.session.is_empty() {
+            problems.push("Session name is empty".to_string());
+        } else if self.session.contains([':', '.']) {
+            problems.push(format!("Session name '{}' can't contain ':' or '.'", self.session));
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            match step {
+                LaunchStep::NewSession { cwd, .. }
+                | LaunchStep::NewWindow { cwd, .. }
+                | LaunchStep::SplitWindow { cwd, .. } => {
+                    if !cwd.is_empty() && !missing.contains(&cwd) && !tmux.directory_exists(cwd) {
+                        problems.push(format!("{}: directory {} does not exist", self.describe(i), cwd));
+                        missing.push(cwd);
+                    }
+                }
+                LaunchStep::SendKeys { line, .. } => {
+                    if line.trim().is_empty() {
+                        problems.push(format!("{}: command is blank", self.describe(i)));
+                    } else if line.contains(|c: char| c.is_control() && c != '\t') {
+                        problems.push(format!("{}: command {:?} spans several lines", self.describe(i), line));
+                    }
                 }
-                SplitType::Vertical => {
-                    tmux::split_window_in_dir(session_name, win_idx, "-h", &pane.cwd)?;
+                LaunchStep::WaitReady { ready, .. } => {
+                    if let Some(problem) = ready.problem() {
+                        problems.push(format!("{}: {}", self.describe(i), problem));
+                    }
                 }
-                SplitType::Full => {}
+                LaunchStep::RunHook(_)
+                | LaunchStep::RenameWindow { .. }
+                | LaunchStep::SelectLayout { .. }
+                | LaunchStep::SetOption { .. }
+                | LaunchStep::UnsetOption { .. }
+                | LaunchStep::RenumberWindows => {}
             }
         }
+        problems
+    }
+
+    /// Fails with every problem `problems` finds.
+    pub fn check(&self, tmux: &dyn TmuxBackend) -> Result<()> {
+        let problems = self.problems(tmux);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(eyre!("Can't launch '{}': {}", self.session, problems.join("; ")))
+        }
+    }
+
+    /// The window, and pane where there is one, that step `i` works on, or
+    /// the session for session-wide steps.
+    fn describe(&self, i: usize) -> String {
+        let step = &self.steps[i];
+        let window = match step {
+            LaunchStep::NewSession { .. } => 0,
+            LaunchStep::RenameWindow { window, .. }
+            | LaunchStep::NewWindow { window, .. }
+            | LaunchStep::SplitWindow { window, .. }
+            | LaunchStep::SelectLayout { window, .. }
+            | LaunchStep::SetOption { window: Some(window), .. }
+            | LaunchStep::SendKeys { window, .. }
+            | LaunchStep::WaitReady { window, .. } => *window,
+            LaunchStep::RunHook(_)
+            | LaunchStep::SetOption { window: None, .. }
+            | LaunchStep::UnsetOption { .. }
+            | LaunchStep::RenumberWindows => {
+                return format!("Session '{}'", self.session);
+            }
+        };
+        let name = self
+            .steps
+            .iter()
+            .find_map(|s| match s {
+                LaunchStep::RenameWindow { window: w, name } | LaunchStep::NewWindow { window: w, name, .. }
+                    if *w == window =>
+                {
+                    Some(name.as_str())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        let pane = match step {
+            LaunchStep::SplitWindow { .. } => {
+                let splits = self.steps[..i]
+                    .iter()
+                    .filter(|s| matches!(s, LaunchStep::SplitWindow { window: w, .. } if *w == window));
+                format!(", pane {}", splits.count() + 1)
+            }
+            LaunchStep::SendKeys { pane, .. } | LaunchStep::WaitReady { pane, .. } => format!(", pane {}", pane),
+            _ => String::new(),
+        };
+        format!("Window {} '{}'{}", window, name, pane)
+    }
+}
+
+/// The steps that create `template` as `session_name`.
+pub fn plan_launch(template: &SessionTemplate, session_name: &str) -> Result<LaunchPlan> {
+    if template.windows.is_empty() {
+        return Err(eyre!("Template has no windows"));
     }
 
+    let mut steps = Vec::new();
+    steps.extend(template.hook("on_create").map(LaunchStep::RunHook));
+    for (wi, win) in template.windows.iter().enumerate() {
+        if wi == 0 {
+            // The session comes with its first window.
+            steps.push(LaunchStep::NewSession {
+                cwd: win.cwd.clone(),
+                environment: template.environment.clone(),
+            });
+            // Windows are addressed by position until all of them exist, but
+            // tmux numbers them from the global base-index, which may be 1.
+            steps.push(LaunchStep::SetOption {
+                window: None,
+                name: "base-index".to_string(),
+                value: "0".to_string(),
+            });
+            steps.push(LaunchStep::RenumberWindows);
+            steps.push(LaunchStep::RenameWindow {
+                window: 0,
+                name: win.name.clone(),
+            });
+            // Hooks that run after the launch are kept on the session.
+            let later: Vec<_> = [template.hook("on_attach"), template.hook("on_stop")].into_iter().flatten().collect();
+            if let Some(hook) = later.first() {
+                steps.push(LaunchStep::SetOption {
+                    window: None,
+                    name: format!("{}hook_cwd", HOOK_OPTION_PREFIX),
+                    value: hook.cwd.clone(),
+                });
+            }
+            for hook in later {
+                steps.push(LaunchStep::SetOption {
+                    window: None,
+                    name: format!("{}{}", HOOK_OPTION_PREFIX, hook.name),
+                    // tmux cuts option values at the first newline.
+                    value: serde_json::Value::from(hook.command).to_string(),
+                });
+            }
+            // Options go first so new panes see them, except base-index,
+            // which waits until the windows are addressed by position no more.
+            for (name, value) in template.options.iter().filter(|(name, _)| *name != "base-index") {
+                steps.push(LaunchStep::SetOption {
+                    window: None,
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        } else {
+            steps.push(LaunchStep::NewWindow {
+                window: wi as u32,
+                name: win.name.clone(),
+                cwd: win.cwd.clone(),
+            });
+        }
+        plan_panes(&mut steps, wi as u32, win)?;
+    }
+    match template.options.get("base-index") {
+        Some(base) => steps.push(LaunchStep::SetOption {
+            window: None,
+            name: "base-index".to_string(),
+            value: base.clone(),
+        }),
+        None => steps.push(LaunchStep::UnsetOption {
+            name: "base-index".to_string(),
+        }),
+    }
+    steps.push(LaunchStep::RenumberWindows);
+
+    Ok(LaunchPlan {
+        session: session_name.to_string(),
+        steps,
+    })
+}
+
+/// Create `template` as `session_name`, all or nothing: the plan is checked
+/// before tmux is touched, and a session left half-built by a failing step is
+/// killed again, with the `on_stop` hook run if `on_create` was. The error
+/// names the step that failed. Returns what the hooks printed, one line each.
+pub fn launch_template(tmux: &dyn TmuxBackend, template: &SessionTemplate, session_name: &str) -> Result<Vec<String>> {
+    launch_template_reporting(tmux, template, session_name, &|_| {})
+}
+
+/// `launch_template`, telling `progress` what it is waiting for while a pane
+/// gets ready.
+pub fn launch_template_reporting(
+    tmux: &dyn TmuxBackend,
+    template: &SessionTemplate,
+    session_name: &str,
+    progress: &dyn Fn(String),
+) -> Result<Vec<String>> {
+    let plan = plan_launch(template, session_name)?;
+    plan.check(tmux)?;
+    let (i, e) = match run_steps(tmux, session_name, &plan.steps, progress) {
+        Ok(output) => return Ok(output),
+        Err(failure) => failure,
+    };
+
+    let failed = format!(
+        "Step {}/{} failed ({}: {}): {}",
+        i + 1,
+        plan.steps.len(),
+        plan.describe(i),
+        plan.commands()[i],
+        e
+    );
+    // Before the session step there is no session of ours to clean up; the
+    // name may even belong to someone else's.
+    let created = plan.steps[..i].iter().any(|s| matches!(s, LaunchStep::NewSession { .. }));
+    if !created {
+        return Err(eyre!(failed));
+    }
+    let mut failed = match tmux.kill_session(session_name) {
+        Ok(()) => format!("{}; session '{}' was removed again", failed, session_name),
+        Err(kill) => format!("{}; session '{}' is left half-built: {}", failed, session_name, kill),
+    };
+    if matches!(plan.steps[0], LaunchStep::RunHook(_))
+        && let Some(hook) = template.hook("on_stop")
+    {
+        match hook.run() {
+            Ok(output) => failed.extend(output.map(|line| format!("; {}", line))),
+            Err(e) => failed.push_str(&format!("; {}", e)),
+        }
+    }
+    Err(eyre!(failed))
+}
+
+/// Run `steps` in order, stopping at the first failure with its index.
+/// Returns what the hooks printed.
+fn run_steps(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    steps: &[LaunchStep],
+    progress: &dyn Fn(String),
+) -> std::result::Result<Vec<String>, (usize, color_eyre::Report)> {
+    // Pane ids per window, listed once its panes are all there.
+    let mut panes = BTreeMap::new();
+    let mut output = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        output.extend(run_step(tmux, session_name, step, &mut panes, progress).map_err(|e| (i, e))?);
+    }
+    Ok(output)
+}
+
+fn run_step(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    step: &LaunchStep,
+    panes: &mut BTreeMap<u32, Vec<TmuxPane>>,
+    progress: &dyn Fn(String),
+) -> Result<Option<String>> {
+    match step {
+        LaunchStep::RunHook(hook) => return hook.run(),
+        LaunchStep::NewSession { cwd, environment } => tmux.new_session_with_cwd(session_name, cwd, environment)?,
+        LaunchStep::SetOption { window, name, value } => tmux.set_option(session_name, *window, name, value)?,
+        LaunchStep::UnsetOption { name } => tmux.unset_option(session_name, name)?,
+        LaunchStep::RenumberWindows => tmux.renumber_windows(session_name)?,
+        LaunchStep::RenameWindow { window, name } => tmux.rename_window(session_name, *window, name)?,
+        LaunchStep::NewWindow { name, cwd, .. } => tmux.new_window_with_cwd(session_name, name, cwd)?,
+        LaunchStep::SplitWindow { window, direction, cwd } => {
+            tmux.split_window_in_dir(session_name, *window, direction, cwd)?
+        }
+        LaunchStep::SelectLayout { window, layout } => tmux.select_layout(session_name, *window, layout)?,
+        LaunchStep::SendKeys { window, pane, line } => {
+            tmux.send_keys(&pane_id(tmux, session_name, panes, *window, *pane)?, line)?
+        }
+        LaunchStep::WaitReady { window, pane, ready } => {
+            let waiting = |left| {
+                progress(format!(
+                    "Waiting for window {} pane {} of '{}' to be ready ({}s left)",
+                    window, pane, session_name, left
+                ))
+            };
+            wait_ready(tmux, &pane_id(tmux, session_name, panes, *window, *pane)?, ready, &waiting)?
+        }
+    }
+    Ok(None)
+}
+
+/// The id of `pane` in `window`, listing the window's panes the first time.
+fn pane_id(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    panes: &mut BTreeMap<u32, Vec<TmuxPane>>,
+    window: u32,
+    pane: usize,
+) -> Result<String> {
+    let listed = match panes.entry(window) {
+        Entry::Occupied(listed) => listed.into_mut(),
+        Entry::Vacant(entry) => entry.insert(tmux.list_panes(session_name, window)?),
+    };
+    listed
+        .get(pane)
+        .map(|p| p.id.clone())
+        .ok_or_else(|| eyre!("Window {} has no pane {}", window, pane))
+}
+
+/// Bring an existing session in line with `template`. Windows are matched by
+/// name, then by position, and renamed to match; missing windows and panes
+/// are created as `launch_template` would, commands included. Panes in the
+/// wrong directory get a `cd` if they are sitting at a shell. Options and
+/// environment variables that differ are set; ones the template doesn't
+/// mention are left alone. With `prune`, windows and panes the template
+/// doesn't have are killed. Returns what was done, one line per change.
+pub fn apply_template(
+    tmux: &dyn TmuxBackend,
+    template: &SessionTemplate,
+    session_name: &str,
+    prune: bool,
+) -> Result<Vec<String>> {
+    if template.windows.is_empty() {
+        return Err(eyre!("Template has no windows"));
+    }
+
+    let existing = tmux.list_windows(session_name)?;
+    let mut used = Vec::new();
+    let mut matched = Vec::new();
+    for win in &template.windows {
+        let found = existing
+            .iter()
+            .find(|e| e.name == win.name && !used.contains(&e.index))
+            .map(|e| e.index);
+        used.extend(found);
+        matched.push(found);
+    }
+    let mut spare = existing.iter().filter(|e| !used.contains(&e.index));
+    for slot in matched.iter_mut().filter(|m| m.is_none()) {
+        *slot = spare.next().map(|e| e.index);
+    }
+    let extra: Vec<_> = spare.collect();
+
+    let mut changes = Vec::new();
+    apply_options(tmux, session_name, None, &template.options, &mut changes)?;
+    let environment = tmux.show_environment(session_name)?;
+    for (name, value) in &template.environment {
+        if environment.get(name) != Some(value) {
+            tmux.set_environment(session_name, name, value)?;
+            changes.push(format!("set environment variable {}", name));
+        }
+    }
+    for (win, index) in template.windows.iter().zip(matched) {
+        match index.and_then(|i| existing.iter().find(|e| e.index == i)) {
+            Some(current) => {
+                if current.name != win.name {
+                    tmux.rename_window(session_name, current.index, &win.name)?;
+                    changes.push(format!("renamed window '{}' to '{}'", current.name, win.name));
+                }
+                apply_options(tmux, session_name, Some(current.index), &win.options, &mut changes)?;
+                apply_panes(tmux, session_name, current.index, &current.layout, win, prune, &mut changes)?;
+            }
+            None => {
+                let before: Vec<_> = tmux.list_windows(session_name)?.iter().map(|w| w.index).collect();
+                tmux.new_window_with_cwd(session_name, &win.name, &win.cwd)?;
+                let index = tmux
+                    .list_windows(session_name)?
+                    .iter()
+                    .map(|w| w.index)
+                    .find(|i| !before.contains(i))
+                    .ok_or_else(|| eyre!("Window '{}' was not created", win.name))?;
+                create_panes(tmux, session_name, index, win)?;
+                changes.push(format!("created window '{}'", win.name));
+            }
+        }
+    }
+
+    if prune {
+        // Highest index first, in case tmux renumbers windows as they go.
+        for win in extra.iter().rev() {
+            tmux.kill_window(session_name, win.index)?;
+            changes.push(format!("killed window '{}'", win.name));
+        }
+    }
+    Ok(changes)
+}
+
+/// Set the options in `wanted` that the session, or with `window` that window,
+/// doesn't already have set to the same value.
+fn apply_options(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    window: Option<u32>,
+    wanted: &BTreeMap<String, String>,
+    changes: &mut Vec<String>,
+) -> Result<()> {
+    if wanted.is_empty() {
+        return Ok(());
+    }
+    let current = tmux.show_options(session_name, window)?;
+    for (name, value) in wanted {
+        if current.get(name) != Some(value) {
+            tmux.set_option(session_name, window, name, value)?;
+            changes.push(match window {
+                Some(index) => format!("set window {} option {} to '{}'", index, name, value),
+                None => format!("set option {} to '{}'", name, value),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The pane half of `apply_template`, for one matched window. Panes are
+/// matched to the template's in index order.
+fn apply_panes(
+    tmux: &dyn TmuxBackend,
+    session_name: &str,
+    win_idx: u32,
+    current_layout: &str,
+    win: &WindowTemplate,
+    prune: bool,
+    changes: &mut Vec<String>,
+) -> Result<()> {
+    let plan = win.pane_plan()?;
+    let panes = tmux.list_panes(session_name, win_idx)?;
+    let wanted = plan.splits.len() + 1;
+
+    for (i, pane) in panes.iter().enumerate().take(wanted) {
+        let cwd = win.panes.get(i).map_or(&win.cwd, |p| &p.cwd);
+        if cwd.is_empty() || cwd.trim_end_matches('/') == pane.cwd.trim_end_matches('/') {
+            continue;
+        }
+        if suggested_command(&pane.command).is_none() {
+            tmux.send_keys(&pane.id, &format!("cd {}", quote(cwd)))?;
+            changes.push(format!("moved pane {} to {}", pane.id, cwd));
+        } else {
+            changes.push(format!("left pane {} in {}: it is running {}", pane.id, pane.cwd, pane.command));
+        }
+    }
+
+    let missing = &plan.splits[panes.len().clamp(1, wanted) - 1..];
+    for (direction, cwd) in missing {
+        tmux.split_window_in_dir(session_name, win_idx, direction, cwd)?;
+        if plan.layout.is_some() {
+            tmux.select_layout(session_name, win_idx, "tiled")?;
+        }
+        changes.push(format!("added a pane to window '{}'", win.name));
+    }
+    let surplus = panes.len().saturating_sub(wanted);
+    if prune {
+        for pane in panes.iter().skip(wanted).rev() {
+            tmux.kill_pane(&pane.id)?;
+            changes.push(format!("killed pane {}", pane.id));
+        }
+    }
+
+    // Re-apply the layout when panes came or went, or sizes drifted. Extra
+    // panes that were kept don't fit it.
+    let reshaped = !missing.is_empty() || (prune && surplus > 0);
+    if let Some(layout) = &plan.layout
+        && (prune || surplus == 0)
+    {
+        let drifted = match (LayoutNode::parse_unchecked(layout), LayoutNode::parse_unchecked(current_layout)) {
+            (Ok(wanted), Ok(current)) => !wanted.same_geometry(&current),
+            _ => false,
+        };
+        if reshaped || drifted {
+            tmux.select_layout(session_name, win_idx, layout)?;
+            if drifted && !reshaped {
+                changes.push(format!("restored the layout of window '{}'", win.name));
+            }
+        }
+    }
+
+    let now = tmux.list_panes(session_name, win_idx)?;
+    for (i, pane) in now.iter().enumerate() {
+        if !panes.iter().any(|p| p.id == pane.id) {
+            for line in win.pane_lines(i) {
+                tmux.send_keys(&pane.id, line)?;
+            }
+        }
+    }
     Ok(())
 }
+
+/// How a window's panes come to be: the split direction and directory of
+/// each pane after the first, then the layout applied once they all exist.
+struct PanePlan<'a> {
+    splits: Vec<(&'static str, &'a str)>,
+    layout: Option<String>,
+}
+
+impl WindowTemplate {
+    /// `self` on top of `base`, field by field: whatever `self` sets wins.
+    fn over(self, base: WindowTemplate) -> WindowTemplate {
+        let or = |own: String, base: String| if own.is_empty() { base } else { own };
+        WindowTemplate {
+            include: None,
+            name: or(self.name, base.name),
+            cwd: or(self.cwd, base.cwd),
+            layout: self.layout.or(base.layout),
+            command: self.command.or(base.command),
+            send_keys: if self.send_keys.is_empty() { base.send_keys } else { self.send_keys },
+            options: {
+                let mut options = base.options;
+                options.extend(self.options);
+                options
+            },
+            panes: if self.panes.is_empty() { base.panes } else { self.panes },
+        }
+    }
+
+    fn pane_plan(&self) -> Result<PanePlan<'_>> {
+        let preset = self.layout.as_deref().filter(|l| PRESETS.contains(l));
+        let layout = self
+            .layout
+            .as_deref()
+            .filter(|_| preset.is_none())
+            .map(LayoutNode::parse_unchecked)
+            .transpose()?;
+        let count = layout.as_ref().map_or(self.panes.len(), |l| l.pane_count());
+
+        let mut splits = Vec::new();
+        for i in 1..count {
+            let pane = self.panes.get(i);
+            let cwd = pane.map_or(self.cwd.as_str(), |p| p.cwd.as_str());
+            // With a layout every cell needs a pane, even a stray "full" one.
+            let direction = match pane.map(|p| &p.split) {
+                Some(SplitType::Horizontal) => "-v",
+                Some(SplitType::Vertical) => "-h",
+                _ if self.layout.is_some() => "-v",
+                _ => continue,
+            };
+            splits.push((direction, cwd));
+        }
+        Ok(PanePlan {
+            splits,
+            layout: layout.map(|l| l.to_string()).or_else(|| preset.map(str::to_string)),
+        })
+    }
+
+    /// Lines typed into the `i`th pane: the window's, then the pane's own.
+    fn pane_lines(&self, i: usize) -> impl Iterator<Item = &String> {
+        let pane = self.panes.get(i);
+        self.command
+            .iter()
+            .chain(&self.send_keys)
+            .chain(pane.and_then(|p| p.command.as_ref()))
+            .chain(pane.map_or(&[][..], |p| &p.send_keys))
+    }
+}
+
+/// Set the window's options, split its first pane into the rest, apply the
+/// saved layout, then type each pane's commands.
+fn plan_panes(steps: &mut Vec<LaunchStep>, window: u32, win: &WindowTemplate) -> Result<()> {
+    let plan = win.pane_plan()?;
+    for (name, value) in &win.options {
+        steps.push(LaunchStep::SetOption {
+            window: Some(window),
+            name: name.clone(),
+            value: value.clone(),
+        });
+    }
+    for (direction, cwd) in &plan.splits {
+        steps.push(LaunchStep::SplitWindow {
+            window,
+            direction,
+            cwd: cwd.to_string(),
+        });
+        if plan.layout.is_some() {
+            // Keep room for the next split; the real layout is applied below.
+            steps.push(LaunchStep::SelectLayout {
+                window,
+                layout: "tiled".to_string(),
+            });
+        }
+    }
+
+    if let Some(layout) = plan.layout {
+        steps.push(LaunchStep::SelectLayout { window, layout });
+    }
+
+    // One pane at a time, in order, so a pane that waits to be ready holds
+    // back every pane after it.
+    for pane in 0..=plan.splits.len() {
+        for line in win.pane_lines(pane) {
+            steps.push(LaunchStep::SendKeys {
+                window,
+                pane,
+                line: line.clone(),
+            });
+        }
+        if let Some(template) = win.panes.get(pane)
+            && let Some(ready) = &template.ready
+        {
+            let mut ready = ready.clone();
+            ready.file = ready.file.map(|file| Path::new(&template.cwd).join(file).display().to_string());
+            steps.push(LaunchStep::WaitReady { window, pane, ready });
+        }
+    }
+    Ok(())
+}
+
+fn create_panes(tmux: &dyn TmuxBackend, session_name: &str, win_idx: u32, win: &WindowTemplate) -> Result<()> {
+    let mut steps = Vec::new();
+    plan_panes(&mut steps, win_idx, win)?;
+    run_steps(tmux, session_name, &steps, &|_| {}).map(|_| ()).map_err(|(_, e)| e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tmux::fake::FakeServer;
+
+    #[test]
+    fn launch_recreates_captured_session() {
+        let server = FakeServer::new();
+        server.new_session_with_cwd("src", "/work", &BTreeMap::new()).unwrap();
+        server.split_window_in_dir("src", 0, "-h", "/work/api").unwrap();
+        server.new_window_with_cwd("src", "logs", "/var/log").unwrap();
+
+        let template = capture_session_as_template(&server, "src", false).unwrap();
+        launch_template(&server, &template, "copy").unwrap();
+
+        let windows: Vec<_> = server
+            .list_windows("copy")
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(windows, ["bash", "logs"]);
+        let cwds: Vec<_> = server
+            .list_panes("copy", 0)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.cwd)
+            .collect();
+        assert_eq!(cwds, ["/work", "/work/api"]);
+    }
+
+    #[test]
+    fn launch_works_under_a_global_base_index() {
+        let server = FakeServer::new();
+        server.new_session_with_cwd("src", "/work", &BTreeMap::new()).unwrap();
+        server.split_window_in_dir("src", 0, "-h", "/work/api").unwrap();
+        server.new_window_with_cwd("src", "logs", "/var/log").unwrap();
+        server.split_window_in_dir("src", 1, "-v", "/var/log/nginx").unwrap();
+        let template = capture_session_as_template(&server, "src", false).unwrap();
+
+        server.set_base_index(1);
+        launch_template(&server, &template, "copy").unwrap();
+
+        let windows: Vec<_> = server.list_windows("copy").unwrap().into_iter().map(|w| (w.index, w.name)).collect();
+        assert_eq!(windows, [(1, "bash".to_string()), (2, "logs".to_string())]);
+        let cwds: Vec<_> = server.list_panes("copy", 2).unwrap().into_iter().map(|p| p.cwd).collect();
+        assert_eq!(cwds, ["/var/log", "/var/log/nginx"]);
+        assert!(!server.show_options("copy", None).unwrap().contains_key("base-index"));
+    }
+
+    #[test]
+    fn launch_applies_captured_layout() {
+        let layout = "d67e,80x24,0,0{40x24,0,0,0,39x24,41,0[39x12,41,0,1,39x11,41,13,2]}";
+        let server = FakeServer::new();
+        server.new_session_with_cwd("src", "/work", &BTreeMap::new()).unwrap();
+        server.split_window_in_dir("src", 0, "-h", "/work").unwrap();
+        server.split_window_in_dir("src", 0, "-v", "/work").unwrap();
+        server.select_layout("src", 0, layout).unwrap();
+
+        let template = capture_session_as_template(&server, "src", false).unwrap();
+        assert_eq!(template.windows[0].layout.as_deref(), Some(layout));
+        launch_template(&server, &template, "copy").unwrap();
+
+        let geometry: Vec<_> = server
+            .list_panes("copy", 0)
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.left, p.top, p.width, p.height))
+            .collect();
+        assert_eq!(geometry, [(0, 0, 40, 24), (41, 0, 39, 12), (41, 13, 39, 11)]);
+        let launched = LayoutNode::parse(&server.list_windows("copy").unwrap()[0].layout).unwrap();
+        assert_eq!(launched.shape(), "{%3 [%4 %5]}");
+    }
+
+    #[test]
+    fn launch_sets_options_and_environment() {
+        let template: SessionTemplate = toml::from_str(
+            r#"
+            options = { status-style = "bg=blue", base-index = 1 }
+            environment = { APP_ENV = "dev" }
+
+            [template]
+            name = "web"
+
+            [[windows]]
+            name = "code"
+            cwd = "/src"
+            options = { synchronize-panes = true, remain-on-exit = "on" }
+            panes = [{ cwd = "/src", split = "full" }]
+
+            [[windows]]
+            name = "logs"
+            cwd = "/var/log"
+            panes = [{ cwd = "/var/log", split = "full" }]
+            "#,
+        )
+        .unwrap();
+        let server = FakeServer::new();
+        launch_template(&server, &template, "web").unwrap();
+
+        let options = server.show_options("web", None).unwrap();
+        assert_eq!(options["status-style"], "bg=blue");
+        assert_eq!(options["base-index"], "1");
+        assert_eq!(server.show_environment("web").unwrap()["APP_ENV"], "dev");
+        let windows: Vec<_> = server.list_windows("web").unwrap().into_iter().map(|w| (w.index, w.name)).collect();
+        assert_eq!(windows, [(1, "code".to_string()), (2, "logs".to_string())]);
+        let code = server.show_options("web", Some(1)).unwrap();
+        assert_eq!(code["synchronize-panes"], "on");
+        assert_eq!(code["remain-on-exit"], "on");
+        assert!(server.show_options("web", Some(2)).unwrap().is_empty());
+
+        let captured = capture_session_as_template(&server, "web", true).unwrap();
+        assert_eq!(captured.options, template.options);
+        assert_eq!(captured.environment, template.environment);
+        assert_eq!(captured.windows[0].options, template.windows[0].options);
+        assert_eq!(capture_session_as_template(&server, "web", false).unwrap().settings_count(), 0);
+    }
+
+    #[test]
+    fn hooks_run_around_the_session() {
+        let dir = std::env::temp_dir().join(format!("tmmx-hooks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut template: SessionTemplate = toml::from_str(&format!(
+            r#"
+            environment = {{ APP_ENV = "dev" }}
+
+            [template]
+            name = "web"
+            variables = {{ service = "db" }}
+
+            [hooks]
+            on_create = "echo $APP_ENV > ${{service}}.up && echo started ${{service}}"
+            on_stop = "rm ${{service}}.up"
+
+            [[windows]]
+            name = "code"
+            cwd = "{}"
+            panes = [{{ cwd = "{}", split = "full" }}]
+            "#,
+            dir.display(),
+            dir.display()
+        ))
+        .unwrap();
+        template = resolve_variables(&template, &BTreeMap::new()).unwrap();
+        let server = FakeServer::new();
+
+        assert_eq!(launch_template(&server, &template, "web").unwrap(), ["on_create: started db"]);
+        assert_eq!(fs::read_to_string(dir.join("db.up")).unwrap(), "dev\n");
+        assert!(session_hook(&server, "web", "on_attach").unwrap().is_none());
+        let on_stop = session_hook(&server, "web", "on_stop").unwrap().unwrap();
+        assert_eq!(on_stop.command, "rm db.up");
+        assert_eq!(on_stop.run().unwrap(), None);
+        assert!(!dir.join("db.up").exists());
+        // A hook that hangs is stopped, along with what it started.
+        let hung = Hook { command: "sleep 5 & sleep 5".to_string(), ..on_stop.clone() };
+        let started = Instant::now();
+        let err = hung.run_within(Duration::from_millis(200)).unwrap_err().to_string();
+        assert_eq!(err, "on_stop hook timed out after 200ms");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(capture_session_as_template(&server, "web", true).unwrap().options.is_empty());
+
+        template.hooks.on_create = Some("echo no database >&2; exit 3".to_string());
+        let err = launch_template(&server, &template, "web2").unwrap_err().to_string();
+        assert!(err.contains("on_create hook failed (exit status: 3): no database"), "{}", err);
+        assert!(server.list_sessions().unwrap().iter().all(|s| s.name != "web2"));
+        let plan = plan_launch(&template.without_hooks(), "web2").unwrap();
+        assert!(plan.steps.iter().all(|s| match s {
+            LaunchStep::RunHook(_) => false,
+            LaunchStep::SetOption { name, .. } => !name.starts_with("@tmmx-"),
+            _ => true,
+        }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn panes_wait_for_the_ones_before_them_to_be_ready() {
+        let dir = std::env::temp_dir().join(format!("tmmx-ready-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("db.pid"), "1").unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut template: SessionTemplate = toml::from_str(&format!(
+            r#"
+            [template]
+            name = "api"
+
+            [[windows]]
+            name = "dev"
+            cwd = "{dir}"
+
+            [[windows.panes]]
+            cwd = "{dir}"
+            split = "full"
+            command = "db start"
+            ready = {{ file = "db.pid", timeout = 0 }}
+
+            [[windows.panes]]
+            cwd = "{dir}"
+            split = "horizontal"
+            command = "server --port {port}"
+            ready = {{ output = "server --port \\d+", port = {port}, timeout = 0 }}
+
+            [[windows.panes]]
+            cwd = "{dir}"
+            split = "horizontal"
+            command = "run-tests"
+            "#,
+            dir = dir.display(),
+            port = port
+        ))
+        .unwrap();
+        let plan = plan_launch(&template, "api").unwrap();
+        let order: Vec<_> = plan
+            .steps
+            .iter()
+            .filter_map(|s| match s {
+                LaunchStep::SendKeys { pane, .. } => Some(format!("send {}", pane)),
+                LaunchStep::WaitReady { pane, .. } => Some(format!("wait {}", pane)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(order, ["send 0", "wait 0", "send 1", "wait 1", "send 2"]);
+        assert_eq!(
+            plan.commands().iter().rfind(|c| c.starts_with("wait")).unwrap(),
+            &format!("wait up to 0s for output matching 'server --port \\d+' in 'api:0.1' and port {}", port)
+        );
+
+        let server = FakeServer::new();
+        launch_template(&server, &template, "api").unwrap();
+        assert_eq!(server.list_panes("api", 0).unwrap().len(), 3);
+
+        drop(listener);
+        let err = launch_template(&server, &template, "api2").unwrap_err().to_string();
+        assert!(err.contains(&format!("Not ready after 0s: no port {}", port)), "{}", err);
+        assert!(err.contains(&format!("the pane last showed 'server --port {}'", port)), "{}", err);
+        assert!(err.ends_with("session 'api2' was removed again"), "{}", err);
+
+        template.windows[0].panes[1].ready.as_mut().unwrap().output = Some("(".to_string());
+        let problems = template_problems(&template);
+        assert!(problems[0].message.starts_with("Window 'dev', pane 1: ready output is not a valid regex"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plan_lists_launch_steps_in_order() {
+        let template: SessionTemplate = toml::from_str(
+            r#"
+            [template]
+            name = "web"
+
+            [[windows]]
+            name = "code"
+            cwd = "/src"
+            layout = "main-vertical"
+            command = "nvm use"
+
+            [[windows.panes]]
+            cwd = "/src"
+            split = "full"
+            command = "vim"
+
+            [[windows.panes]]
+            cwd = "/src/docs"
+            split = "vertical"
+
+            [[windows]]
+            name = "logs"
+            cwd = "/var/log"
+            panes = []
+            "#,
+        )
+        .unwrap();
+
+        let plan = plan_launch(&template, "web").unwrap();
+        assert_eq!(
+            plan.commands(),
+            [
+                "new-session -d -s 'web' -c '/src'",
+                "set-option -t 'web' 'base-index' '0'",
+                "move-window -r -t 'web'",
+                "rename-window -t 'web:0' 'code'",
+                "split-window -h -t 'web:0' -c '/src/docs'",
+                "select-layout -t 'web:0' 'tiled'",
+                "select-layout -t 'web:0' 'main-vertical'",
+                "send-keys -t 'web:0.0' -l 'nvm use' ; send-keys -t 'web:0.0' Enter",
+                "send-keys -t 'web:0.0' -l 'vim' ; send-keys -t 'web:0.0' Enter",
+                "send-keys -t 'web:0.1' -l 'nvm use' ; send-keys -t 'web:0.1' Enter",
+                "new-window -d -t 'web' -n 'logs' -c '/var/log'",
+                "set-option -u -t 'web' 'base-index'",
+                "move-window -r -t 'web'",
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_reconciles_a_drifted_session() {
+        let server = FakeServer::new();
+        server.new_session_with_cwd("src", "/work", &BTreeMap::new()).unwrap();
+        server.split_window_in_dir("src", 0, "-h", "/work/api").unwrap();
+        server.new_window_with_cwd("src", "logs", "/var/log").unwrap();
+        let mut template = capture_session_as_template(&server, "src", false).unwrap();
+        template.windows[0].name = "code".to_string();
+        template.windows[0].panes[1].command = Some("make run".to_string());
+
+        // One pane short, the first window renamed, and a window too many.
+        server.new_session_with_cwd("dev", "/work", &BTreeMap::new()).unwrap();
+        server.new_window_with_cwd("dev", "scratch", "/tmp").unwrap();
+        server.new_window_with_cwd("dev", "logs", "/var/log").unwrap();
+
+        let changes = apply_template(&server, &template, "dev", false).unwrap();
+        assert_eq!(changes, ["renamed window 'bash' to 'code'", "added a pane to window 'code'"]);
+        let panes = server.list_panes("dev", 0).unwrap();
+        let cwds: Vec<_> = panes.iter().map(|p| p.cwd.as_str()).collect();
+        assert_eq!(cwds, ["/work", "/work/api"]);
+        assert_eq!(server.capture_pane(&panes[1].id).unwrap(), "make run\n");
+
+        let changes = apply_template(&server, &template, "dev", true).unwrap();
+        assert_eq!(changes, ["killed window 'scratch'"]);
+        let names: Vec<_> = server.list_windows("dev").unwrap().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, ["code", "logs"]);
+        assert!(apply_template(&server, &template, "dev", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn variables_resolve_from_values_defaults_and_env() {
+        let template: SessionTemplate = toml::from_str(
+            r#"
+            [template]
+            name = "${project}"
+
+            [template.variables]
+            root = "${env:HOME}/src"
+            branch = ""
+
+            [[windows]]
+            name = "code"
+            cwd = "${root}/${project}"
+            command = "git checkout ${branch} && echo $${HOME} ${env:HOME}"
+            panes = []
+            "#,
+        )
+        .unwrap();
+        assert_eq!(unset_variables(&template), ["project", "branch"]);
+
+        let values = BTreeMap::from([
+            ("project".to_string(), "tmmx".to_string()),
+            ("branch".to_string(), "main".to_string()),
+        ]);
+        let resolved = resolve_variables(&template, &values).unwrap();
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(resolved.template.name, "tmmx");
+        assert_eq!(resolved.windows[0].cwd, format!("{}/src/tmmx", home));
+        assert_eq!(
+            resolved.windows[0].command,
+            Some(format!("git checkout main && echo ${{HOME}} {}", home))
+        );
+
+        let err = resolve_variables(&template, &BTreeMap::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Template variable 'project' has no value");
+    }
+
+    #[test]
+    fn launch_types_window_then_pane_commands() {
+        let template: SessionTemplate = toml::from_str(
+            r#"
+            [template]
+            name = "dev"
+
+            [[windows]]
+            name = "code"
+            cwd = "/work"
+            command = "source .env"
+
+            [[windows.panes]]
+            cwd = "/work"
+            split = "full"
+            command = "cargo watch"
+
+            [[windows.panes]]
+            cwd = "/work"
+            split = "vertical"
+            send_keys = ["git status", "git log -1"]
+            "#,
+        )
+        .unwrap();
+        let server = FakeServer::new();
+        launch_template(&server, &template, "dev").unwrap();
+
+        let panes = server.list_panes("dev", 0).unwrap();
+        let contents: Vec<_> = panes.iter().map(|p| server.capture_pane(&p.id).unwrap()).collect();
+        assert_eq!(
+            contents,
+            ["source .env\ncargo watch\n", "source .env\ngit status\ngit log -1\n"]
+        );
+    }
+
+    #[test]
+    fn failed_launch_removes_the_partial_session() {
+        let mut template: SessionTemplate = toml::from_str(
+            r#"
+            [template]
+            name = "dev"
+
+            [[windows]]
+            name = "code"
+            cwd = "/work"
+
+            [[windows.panes]]
+            cwd = "/work"
+            split = "full"
+
+            [[windows]]
+            name = "logs"
+            cwd = "/var/log"
+            panes = []
+            "#,
+        )
+        .unwrap();
+        template.windows[1].panes = (0..12)
+            .map(|i| PaneTemplate {
+                cwd: "/var/log".to_string(),
+                split: if i == 0 { SplitType::Full } else { SplitType::Vertical },
+                command: None,
+                send_keys: Vec::new(),
+                ready: None,
+            })
+            .collect();
+        let server = FakeServer::new();
+        server.new_session("other").unwrap();
+
+        let err = launch_template(&server, &template, "dev").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Step 11/18 failed (Window 1 'logs', pane 6: split-window -h -t 'dev:1' -c '/var/log'): \
+             no space for new pane; session 'dev' was removed again"
+        );
+        let sessions: Vec<_> = server.list_sessions().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(sessions, ["other"]);
+
+        // Checked up front, nothing is created at all.
+        server.remove_dir("/var/log");
+        let err = launch_template(&server, &template, "a.b").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Can't launch 'a.b': Session name 'a.b' can't contain ':' or '.'; \
+             Window 1 'logs': directory /var/log does not exist"
+        );
+        assert_eq!(server.list_sessions().unwrap().len(), 1);
+    }
+
+    fn check(content: &str) -> (Option<SessionTemplate>, Vec<TemplateProblem>) {
+        let (template, mut problems) = parse_toml::<SessionTemplate>(content);
+        if let Some(t) = &template {
+            problems.extend(template_problems(t));
+        }
+        (template, problems)
+    }
+
+    #[test]
+    fn check_reports_parse_errors_and_template_problems() {
+        let (template, problems) = check("[template]\nname = \"web\"\n\n[[windows]]\nname = code\n");
+        assert!(template.is_none());
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].position, Some((5, 8)));
+
+        let (template, problems) = check(
+            r#"
+[template]
+name = "web"
+
+[[windows]]
+name = "code"
+cwd = "/"
+comand = "vim"
+panes = [
+    { cwd = "/", split = "vertical" },
+    { cwd = "/nonexistent-tmmx", split = "full" },
+]
+
+[[windows]]
+name = "code"
+cwd = "/"
+layout = "spiral"
+panes = [{ cwd = "/", split = "full", comand = "ls" }]
+"#,
+        );
+        assert!(template.is_some());
+        let problems: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "8:1: Unknown key 'windows.0.comand'",
+                "18:39: Unknown key 'windows.1.panes.0.comand'",
+                "Window 'code': the first pane must have split = \"full\"",
+                "Window 'code', pane 1: only the first pane can have split = \"full\"",
+                "Window name 'code' is used more than once",
+                "Window 'code': invalid layout: expected a number at offset 0",
+                "Window 'code', pane 1: directory /nonexistent-tmmx does not exist",
+            ]
+        );
+
+        let (_, problems) = check("windows = []\n[template]\nname = \"empty\"\n");
+        assert_eq!(problems, [TemplateProblem::new("Template has no windows".to_string())]);
+    }
+
+    #[test]
+    fn project_templates_are_found_above_the_working_directory() {
+        let root = std::env::temp_dir().join(format!("tmmx-project-{}", std::process::id()));
+        let nested = root.join("src").join("api");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(root.join(".tmmx").join("templates")).unwrap();
+        fs::write(
+            root.join(".tmmx.toml"),
+            "[template]\nname = \"repo\"\n[[windows]]\nname = \"code\"\ncwd = \"\"\npanes = [{ cwd = \"src\", split = \"full\" }]\n",
+        )
+        .unwrap();
+        fs::write(root.join(".tmmx").join("templates").join("docs.toml"), "[template]\n").unwrap();
+
+        let (found, paths) = find_project_templates(&nested).unwrap();
+        assert_eq!(found, root);
+        assert_eq!(paths, [root.join(".tmmx.toml"), root.join(".tmmx/templates/docs.toml")]);
+
+        let file = TemplateFile::read(&paths[0], Some(&found));
+        let win = &file.template.as_ref().unwrap().windows[0];
+        assert_eq!(win.cwd, root.display().to_string());
+        assert_eq!(win.panes[0].cwd, root.join("src").display().to_string());
+        assert!(file.problems.is_empty(), "{:?}", file.problems);
+        assert_eq!(TemplateFile::read(&paths[1], Some(&found)).name(), "docs");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn extends_and_includes_merge_field_by_field() {
+        let dir = std::env::temp_dir().join(format!("tmmx-extends-{}", std::process::id()));
+        fs::create_dir_all(dir.join("windows")).unwrap();
+        fs::write(
+            dir.join("windows").join("logs.toml"),
+            "name = \"logs\"\ncwd = \"/var/log\"\ncommand = \"tail -f syslog\"\npanes = [{ cwd = \"/var/log\", split = \"full\" }]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("base.toml"),
+            r#"
+            [template]
+            name = "base"
+            description = "Editor and shell"
+            variables = { root = "/src", editor = "vim" }
+
+            [[windows]]
+            name = "code"
+            cwd = "${root}"
+            command = "${editor}"
+            panes = [{ cwd = "${root}", split = "full" }]
+
+            [[windows]]
+            name = "shell"
+            cwd = "${root}"
+            panes = [{ cwd = "${root}", split = "full" }]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("dev.toml"),
+            r#"
+            [template]
+            name = "dev"
+            extends = "base"
+            variables = { editor = "hx" }
+
+            [[windows]]
+            name = "shell"
+            cwd = "/tmp"
+
+            [[windows]]
+            include = "windows/logs.toml"
+            command = "journalctl -f"
+            "#,
+        )
+        .unwrap();
+
+        let library: Vec<_> = ["base.toml", "dev.toml"]
+            .iter()
+            .map(|f| TemplateFile::read(&dir.join(f), None))
+            .collect();
+        let mut dev = library[1].clone();
+        dev.resolve(&library);
+        let t = dev.template.unwrap();
+        assert_eq!(t.template.description, "Editor and shell");
+        assert_eq!(t.template.extends, None);
+        assert_eq!(t.template.variables["editor"], "hx");
+        assert_eq!(t.template.variables["root"], "/src");
+        let windows: Vec<_> = t
+            .windows
+            .iter()
+            .map(|w| (w.name.as_str(), w.cwd.as_str(), w.command.as_deref(), w.panes.len()))
+            .collect();
+        assert_eq!(
+            windows,
+            [
+                ("code", "${root}", Some("${editor}"), 1),
+                ("shell", "/tmp", None, 1),
+                ("logs", "/var/log", Some("journalctl -f"), 1),
+            ]
+        );
+
+        fs::write(dir.join("base.toml"), "windows = []\n[template]\nname = \"base\"\nextends = \"dev\"\n").unwrap();
+        let library: Vec<_> = ["base.toml", "dev.toml"]
+            .iter()
+            .map(|f| TemplateFile::read(&dir.join(f), None))
+            .collect();
+        let mut dev = library[1].clone();
+        dev.resolve(&library);
+        assert_eq!(dev.problems[0].message, "Template 'dev' ends up extending itself");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
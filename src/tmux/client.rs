@@ -0,0 +1,594 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{Result, eyre};
+
+use crate::model::{PaneEntry, TmuxPane, TmuxSession, TmuxWindow};
+
+use super::{Socket, TmuxBackend, is_inside_tmux};
+use super::control::{ControlClient, Notification, Response};
+
+const FIELD_SEP: &str = "|||";
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+/// Fields `parse_pane` reads, in order.
+const PANE_FORMAT: [&str; 11] = [
+    "#{pane_id}",
+    "#{pane_index}",
+    "#{pane_active}",
+    "#{pane_current_command}",
+    "#{pane_width}",
+    "#{pane_height}",
+    "#{pane_top}",
+    "#{pane_left}",
+    "#{pane_current_path}",
+    "#{pane_pid}",
+    "#{pane_title}",
+];
+
+struct Connection {
+    client: Option<ControlClient>,
+    last_attempt: Option<Instant>,
+    notify: Option<Sender<(Socket, Notification)>>,
+    /// Session the control client was last switched to via `follow_session`.
+    followed: Option<String>,
+    /// Pane the control client alone gets output from, via `follow_pane`.
+    followed_pane: Option<String>,
+}
+
+/// The real backend: commands go over a shared control-mode client when one
+/// can be attached, and fall back to spawning `tmux` otherwise.
+pub struct TmuxClient {
+    socket: Socket,
+    /// Whether to attach a control client at all.
+    control: bool,
+    connection: Mutex<Connection>,
+    socket_path: OnceLock<Option<String>>,
+}
+
+impl TmuxClient {
+    pub fn new(socket: Socket) -> Self {
+        Self {
+            socket,
+            control: true,
+            socket_path: OnceLock::new(),
+            connection: Mutex::new(Connection {
+                client: None,
+                last_attempt: None,
+                notify: None,
+                followed: None,
+                followed_pane: None,
+            }),
+        }
+    }
+
+    /// A client that only ever spawns `tmux` processes, for work off the UI
+    /// thread. Another control client would show up as an attached client and
+    /// run the user's client hooks.
+    pub fn without_control(socket: Socket) -> Self {
+        Self { control: false, ..Self::new(socket) }
+    }
+
+    /// Deliver control-mode notifications to `tx`. Should be called before the
+    /// first tmux command so the initial connection has the sender attached.
+    pub fn subscribe(&self, tx: Sender<(Socket, Notification)>) {
+        if let Ok(mut conn) = self.connection.lock() {
+            conn.notify = Some(tx);
+            // Reconnect lazily so an existing client picks up the sender.
+            conn.client = None;
+        }
+    }
+
+    /// Run a command over the shared control-mode client, connecting lazily.
+    /// Returns `None` when no control client is available so callers can fall
+    /// back to spawning a one-off `tmux` process.
+    fn run_control(&self, args: &[&str]) -> Option<Response> {
+        if !self.control {
+            return None;
+        }
+        let mut conn = self.connection.lock().ok()?;
+
+        if conn.client.is_none() {
+            if conn
+                .last_attempt
+                .is_some_and(|t| t.elapsed() < RECONNECT_INTERVAL)
+            {
+                return None;
+            }
+            conn.last_attempt = Some(Instant::now());
+            conn.client = ControlClient::connect(&self.socket, conn.notify.clone()).ok();
+            conn.followed = None;
+            conn.followed_pane = None;
+        }
+
+        let response = conn.client.as_mut()?.command(args);
+        match response {
+            Ok(r) => Some(r),
+            Err(_) => {
+                // Connection is broken (e.g. its session was killed); drop it so
+                // the next call reconnects.
+                conn.client = None;
+                None
+            }
+        }
+    }
+
+    fn control_session_id(&self) -> Option<String> {
+        self.connection
+            .lock()
+            .ok()
+            .and_then(|c| c.client.as_ref().and_then(|c| c.session_id()))
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String> {
+        match self.run_control(args) {
+            Some(Response::Output(out)) => Ok(out),
+            Some(Response::Error(err)) => Err(eyre!("{}", err)),
+            None => self.run_process(args),
+        }
+    }
+
+    fn run_allow_empty(&self, args: &[&str]) -> Result<String> {
+        match self.run_control(args) {
+            Some(Response::Output(out)) => Ok(out),
+            Some(Response::Error(err)) => Err(eyre!("{}", err)),
+            None => {
+                let output = self.socket.command().args(args).output()?;
+                if output.status.success() || output.status.code() == Some(1) {
+                    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+                } else {
+                    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                    Err(eyre!("{}", stderr))
+                }
+            }
+        }
+    }
+
+    /// Spawn a one-off `tmux` process. Used as a fallback and for commands that
+    /// act on the calling client (e.g. `switch-client`), which must not be sent
+    /// through the control client.
+    fn run_process(&self, args: &[&str]) -> Result<String> {
+        let output = self.socket.command().args(args).output()?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(eyre!("{}", stderr))
+        }
+    }
+
+    /// The server's socket path as reported by tmux, resolved once.
+    fn socket_path(&self) -> Option<&str> {
+        self.socket_path
+            .get_or_init(|| {
+                self.run(&["display-message", "-p", "#{socket_path}"])
+                    .ok()
+                    .map(|p| p.trim().to_string())
+            })
+            .as_deref()
+    }
+}
+
+fn parse_pane(fields: &[&str]) -> Option<TmuxPane> {
+    if fields.len() < PANE_FORMAT.len() {
+        return None;
+    }
+    Some(TmuxPane {
+        id: fields[0].to_string(),
+        index: fields[1].parse().unwrap_or(0),
+        active: fields[2] != "0",
+        command: fields[3].to_string(),
+        width: fields[4].parse().unwrap_or(0),
+        height: fields[5].parse().unwrap_or(0),
+        top: fields[6].parse().unwrap_or(0),
+        left: fields[7].parse().unwrap_or(0),
+        cwd: fields[8].to_string(),
+        pid: fields[9].parse().unwrap_or(0),
+        title: fields[10].to_string(),
+    })
+}
+
+impl TmuxBackend for TmuxClient {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>> {
+        let format = [
+            "#{session_name}",
+            "#{session_id}",
+            "#{session_windows}",
+            "#{session_attached}",
+            "#{session_created}",
+            "#{session_activity}",
+        ]
+        .join(FIELD_SEP);
+
+        let output = self.run_allow_empty(&["list-sessions", "-F", &format])?;
+        let control_session = self.control_session_id();
+        let mut sessions = Vec::new();
+
+        for line in output.lines() {
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(FIELD_SEP).collect();
+            if fields.len() < 6 {
+                continue;
+            }
+            let mut attached: u32 = fields[3].parse().unwrap_or(0);
+            if control_session.as_deref() == Some(fields[1]) {
+                attached = attached.saturating_sub(1);
+            }
+            sessions.push(TmuxSession {
+                server: self.socket.clone(),
+                name: fields[0].to_string(),
+                id: fields[1].to_string(),
+                windows: fields[2].parse().unwrap_or(0),
+                attached: attached > 0,
+                created: fields[4].parse().unwrap_or(0),
+                last_activity: fields[5].parse().unwrap_or(0),
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    fn list_windows(&self, session: &str) -> Result<Vec<TmuxWindow>> {
+        let format = [
+            "#{window_name}",
+            "#{window_index}",
+            "#{window_id}",
+            "#{window_active}",
+            "#{window_panes}",
+            "#{window_layout}",
+            "#{window_flags}",
+        ]
+        .join(FIELD_SEP);
+
+        let output = self.run(&["list-windows", "-t", session, "-F", &format])?;
+        let mut windows = Vec::new();
+
+        for line in output.lines() {
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(FIELD_SEP).collect();
+            if fields.len() < 7 {
+                continue;
+            }
+            windows.push(TmuxWindow {
+                name: fields[0].to_string(),
+                index: fields[1].parse().unwrap_or(0),
+                id: fields[2].to_string(),
+                active: fields[3] != "0",
+                panes: fields[4].parse().unwrap_or(0),
+                layout: fields[5].to_string(),
+                flags: fields[6].to_string(),
+            });
+        }
+
+        Ok(windows)
+    }
+
+    fn list_panes(&self, session: &str, window_index: u32) -> Result<Vec<TmuxPane>> {
+        let target = format!("{}:{}", session, window_index);
+        let output = self.run(&["list-panes", "-t", &target, "-F", &PANE_FORMAT.join(FIELD_SEP)])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| parse_pane(&line.split(FIELD_SEP).collect::<Vec<_>>()))
+            .collect())
+    }
+
+    fn list_all_panes(&self) -> Result<Vec<PaneEntry>> {
+        let format = [&["#{session_name}", "#{window_index}", "#{window_name}"][..], &PANE_FORMAT[..]]
+            .concat()
+            .join(FIELD_SEP);
+        let output = self.run_allow_empty(&["list-panes", "-a", "-F", &format])?;
+        let mut entries = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split(FIELD_SEP).collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            if let Some(pane) = parse_pane(&fields[3..]) {
+                entries.push(PaneEntry {
+                    session: fields[0].to_string(),
+                    window_index: fields[1].parse().unwrap_or(0),
+                    window_name: fields[2].to_string(),
+                    pane,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    fn capture_pane(&self, pane_id: &str) -> Result<String> {
+        self.run(&["capture-pane", "-t", pane_id, "-p"])
+    }
+
+    fn capture_scrollback(&self, pane_id: &str, depth: Option<usize>, escapes: bool) -> Result<String> {
+        let start = depth.map_or("-".to_string(), |lines| format!("-{}", lines));
+        let mut args = vec!["capture-pane", "-t", pane_id, "-p", "-S", &start];
+        if escapes {
+            args.push("-e");
+        }
+        self.run(&args)
+    }
+
+    fn copy_mode_at(&self, pane_id: &str, line: usize) -> Result<()> {
+        let history = self.run(&["display-message", "-p", "-t", pane_id, "#{history_size}"])?;
+        let history: usize = history.trim().parse().unwrap_or(0);
+        // goto-line scrolls that many lines back, leaving the line on the top
+        // row; lines on the visible screen are reached by moving down.
+        let scroll = history.saturating_sub(line);
+        let row = line + scroll - history;
+        self.run(&["copy-mode", "-t", pane_id])?;
+        self.run(&["send-keys", "-t", pane_id, "-X", "goto-line", &scroll.to_string()])?;
+        self.run(&["send-keys", "-t", pane_id, "-X", "top-line"])?;
+        if row > 0 {
+            self.run(&["send-keys", "-t", pane_id, "-X", "-N", &row.to_string(), "cursor-down"])?;
+        }
+        Ok(())
+    }
+
+    fn new_session(&self, name: &str) -> Result<()> {
+        self.run(&["new-session", "-d", "-s", name])?;
+        Ok(())
+    }
+
+    fn new_session_with_cwd(&self, name: &str, cwd: &str, environment: &BTreeMap<String, String>) -> Result<()> {
+        let vars: Vec<String> = environment.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        let mut args = vec!["new-session", "-d", "-s", name, "-c", cwd];
+        for var in &vars {
+            args.extend(["-e", var.as_str()]);
+        }
+        self.run(&args)?;
+        Ok(())
+    }
+
+    fn kill_session(&self, name: &str) -> Result<()> {
+        self.run(&["kill-session", "-t", name])?;
+        Ok(())
+    }
+
+    fn rename_session(&self, old: &str, new: &str) -> Result<()> {
+        self.run(&["rename-session", "-t", old, new])?;
+        Ok(())
+    }
+
+    fn new_window(&self, session: &str, name: Option<&str>) -> Result<()> {
+        match name {
+            Some(n) if !n.is_empty() => self.run(&["new-window", "-d", "-t", session, "-n", n])?,
+            _ => self.run(&["new-window", "-d", "-t", session])?,
+        };
+        Ok(())
+    }
+
+    fn new_window_with_cwd(&self, session: &str, name: &str, cwd: &str) -> Result<()> {
+        self.run(&["new-window", "-d", "-t", session, "-n", name, "-c", cwd])?;
+        Ok(())
+    }
+
+    fn split_window_in_dir(&self, session: &str, window_index: u32, direction: &str, cwd: &str) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+        self.run(&["split-window", direction, "-t", &target, "-c", cwd])?;
+        Ok(())
+    }
+
+    fn kill_window(&self, session: &str, window_index: u32) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+        self.run(&["kill-window", "-t", &target])?;
+        Ok(())
+    }
+
+    fn rename_window(&self, session: &str, window_index: u32, new_name: &str) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+        self.run(&["rename-window", "-t", &target, new_name])?;
+        Ok(())
+    }
+
+    fn split_window_horizontal(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()> {
+        let _ = (session, window_index);
+        self.run(&["split-window", "-v", "-t", pane_id])?;
+        Ok(())
+    }
+
+    fn split_window_vertical(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()> {
+        let _ = (session, window_index);
+        self.run(&["split-window", "-h", "-t", pane_id])?;
+        Ok(())
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        self.run(&["kill-pane", "-t", pane_id])?;
+        Ok(())
+    }
+
+    fn select_pane(&self, pane_id: &str) -> Result<()> {
+        self.run(&["select-pane", "-t", pane_id])?;
+        Ok(())
+    }
+
+    fn resize_pane_zoom(&self, pane_id: &str) -> Result<()> {
+        self.run(&["resize-pane", "-Z", "-t", pane_id])?;
+        Ok(())
+    }
+
+    fn break_pane(&self, pane_id: &str) -> Result<()> {
+        self.run(&["break-pane", "-d", "-s", pane_id])?;
+        Ok(())
+    }
+
+    fn send_keys(&self, pane_id: &str, line: &str) -> Result<()> {
+        // -l so words like "Enter" or "C-c" in the line are typed literally.
+        self.run(&["send-keys", "-t", pane_id, "-l", line])?;
+        self.run(&["send-keys", "-t", pane_id, "Enter"])?;
+        Ok(())
+    }
+
+    fn switch_client(&self, target: &str) -> Result<()> {
+        self.run_process(&["switch-client", "-t", target])?;
+        Ok(())
+    }
+
+    fn select_window(&self, session: &str, window_index: u32) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+        self.run(&["select-window", "-t", &target])?;
+        Ok(())
+    }
+
+    fn renumber_windows(&self, session: &str) -> Result<()> {
+        self.run(&["move-window", "-r", "-t", session])?;
+        Ok(())
+    }
+
+    fn set_option(&self, session: &str, window: Option<u32>, name: &str, value: &str) -> Result<()> {
+        match window {
+            Some(index) => {
+                let target = format!("{}:{}", session, index);
+                self.run(&["set-option", "-w", "-t", &target, name, value])?
+            }
+            None => self.run(&["set-option", "-t", session, name, value])?,
+        };
+        Ok(())
+    }
+
+    fn unset_option(&self, session: &str, name: &str) -> Result<()> {
+        self.run(&["set-option", "-u", "-t", session, name])?;
+        Ok(())
+    }
+
+    fn show_options(&self, session: &str, window: Option<u32>) -> Result<BTreeMap<String, String>> {
+        let output = match window {
+            Some(index) => {
+                let target = format!("{}:{}", session, index);
+                self.run_allow_empty(&["show-options", "-w", "-t", &target])?
+            }
+            None => self.run_allow_empty(&["show-options", "-t", session])?,
+        };
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, value)| (name.to_string(), unquote(value)))
+            .collect())
+    }
+
+    fn set_environment(&self, session: &str, name: &str, value: &str) -> Result<()> {
+        self.run(&["set-environment", "-t", session, name, value])?;
+        Ok(())
+    }
+
+    fn show_environment(&self, session: &str) -> Result<BTreeMap<String, String>> {
+        let copied = self.run_allow_empty(&["show-options", "-gv", "update-environment"])?;
+        let copied: Vec<&str> = copied.lines().collect();
+        let output = self.run_allow_empty(&["show-environment", "-t", session])?;
+        // `-NAME` marks a variable removed from the session's environment.
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter(|(name, _)| !copied.contains(name))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+
+    fn select_layout(&self, session: &str, window_index: u32, layout: &str) -> Result<()> {
+        let target = format!("{}:{}", session, window_index);
+        self.run(&["select-layout", "-t", &target, layout])?;
+        Ok(())
+    }
+
+    /// Attach the control client to `session`. tmux only reports layout changes
+    /// and pane output for the attached session, so this keeps notifications
+    /// flowing for whatever the user is looking at.
+    fn follow_session(&self, session: &str) {
+        let followed = self
+            .connection
+            .lock()
+            .map(|c| c.client.is_some() && c.followed.as_deref() == Some(session))
+            .unwrap_or(false);
+        if followed {
+            return;
+        }
+        if let Some(Response::Output(_)) = self.run_control(&["switch-client", "-t", session])
+            && let Ok(mut conn) = self.connection.lock()
+        {
+            conn.followed = Some(session.to_string());
+            conn.followed_pane = None;
+        }
+    }
+
+    /// Turn output off for every pane of the followed session but `pane_id`,
+    /// so a busy pane elsewhere doesn't wake the UI. tmmx's own pane stays
+    /// off, or its redraws would come back as output.
+    fn follow_pane(&self, pane_id: &str) {
+        let session = match self.connection.lock() {
+            Ok(c) if c.client.is_some() && c.followed_pane.as_deref() != Some(pane_id) => c.followed.clone(),
+            _ => None,
+        };
+        let Some(session) = session else {
+            return;
+        };
+        let Ok(panes) = self.run(&["list-panes", "-s", "-t", &session, "-F", "#{pane_id}"]) else {
+            return;
+        };
+        let own = std::env::var("TMUX_PANE").ok().filter(|_| self.contains_current_client());
+        let states: Vec<String> = panes
+            .lines()
+            .map(|p| {
+                let on = p == pane_id && own.as_deref() != Some(p);
+                format!("{}:{}", p, if on { "on" } else { "off" })
+            })
+            .collect();
+        let mut args = vec!["refresh-client"];
+        for state in &states {
+            args.extend(["-A", state.as_str()]);
+        }
+        if let Some(Response::Output(_)) = self.run_control(&args)
+            && let Ok(mut conn) = self.connection.lock()
+        {
+            conn.followed_pane = Some(pane_id.to_string());
+        }
+    }
+
+    fn socket(&self) -> Socket {
+        self.socket.clone()
+    }
+
+    fn contains_current_client(&self) -> bool {
+        if !is_inside_tmux() {
+            return false;
+        }
+        // Plain `tmux` resolves to $TMUX's server, so Default always matches.
+        if self.socket == Socket::Default {
+            return true;
+        }
+        let env = std::env::var("TMUX").unwrap_or_default();
+        let current = env.split(',').next().unwrap_or_default();
+        self.socket_path() == Some(current)
+    }
+
+    fn is_control_connected(&self) -> bool {
+        self.connection
+            .lock()
+            .map(|mut c| c.client.as_mut().is_some_and(|c| c.is_alive()))
+            .unwrap_or(false)
+    }
+}
+
+/// An option value as `show-options` prints it: bare, or quoted when it has
+/// spaces or special characters, with `\` escapes inside double quotes.
+fn unquote(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.to_string();
+    }
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
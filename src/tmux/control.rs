@@ -0,0 +1,256 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::{Socket, current_socket_path, quote};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result of a single command sent over the control connection.
+pub enum Response {
+    Output(String),
+    Error(String),
+}
+
+/// Asynchronous `%` notifications tmux sends to control clients, reduced to
+/// what the UI needs to decide which lists to refresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    SessionsChanged,
+    WindowsChanged,
+    /// Pane geometry or active pane changed in the given window (`@id`).
+    LayoutChanged(String),
+    /// The given pane (`%id`) produced output.
+    Output(String),
+}
+
+/// A long-lived `tmux -C` client. Commands are written to its stdin and the
+/// matching `%begin`/`%end` (or `%error`) block is read back from stdout, so
+/// each query costs a pipe round-trip instead of a fork.
+pub struct ControlClient {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<Response>,
+    session_id: Arc<Mutex<Option<String>>>,
+}
+
+impl ControlClient {
+    /// Notifications are sent tagged with `socket`: ids like `%0` are only
+    /// unique within one server.
+    pub fn connect(
+        socket: &Socket,
+        notify: Option<Sender<(Socket, Notification)>>,
+    ) -> io::Result<Self> {
+        // ignore-size keeps the control client from shrinking windows to its
+        // default 80x24.
+        let mut child = socket
+            .command()
+            .args(["-C", "attach-session", "-f", "ignore-size"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("no stdout"))?;
+
+        let (tx, responses) = mpsc::channel();
+        let session_id = Arc::new(Mutex::new(None));
+        let reader_session = Arc::clone(&session_id);
+        let notify = notify.map(|tx| (tx, socket.clone()));
+        thread::spawn(move || {
+            read_loop(BufReader::new(stdout), tx, reader_session, notify.as_ref());
+            // The client is detached when its session is destroyed. Nudge the
+            // UI so its next refresh reconnects.
+            if let Some((notify, socket)) = notify {
+                let _ = notify.send((socket, Notification::SessionsChanged));
+            }
+        });
+
+        let mut client = Self {
+            child,
+            stdin,
+            responses,
+            session_id,
+        };
+
+        // The first block answers the attach-session itself.
+        if let Response::Error(e) = client.wait_response()? {
+            return Err(io::Error::other(e));
+        }
+
+        // %session-changed arrives after the attach reply; ask directly so the
+        // id is known before the first list-sessions.
+        let info = client.command(&["display-message", "-p", "#{session_id} #{socket_path}"])?;
+        let Response::Output(info) = info else {
+            return Ok(client);
+        };
+        let (id, path) = info.trim().split_once(' ').unwrap_or((info.trim(), ""));
+        if let Ok(mut current) = client.session_id.lock() {
+            *current = Some(id.to_string());
+        }
+
+        // Every redraw of tmmx's own pane would come back as %output and wake
+        // the UI to redraw again.
+        if current_socket_path().is_some_and(|p| p.as_os_str() == path)
+            && let Ok(pane) = std::env::var("TMUX_PANE")
+        {
+            client.command(&["refresh-client", "-A", &format!("{}:off", pane)])?;
+        }
+
+        Ok(client)
+    }
+
+    /// Send one command and wait for its reply. An `Err` means the connection
+    /// itself is unusable; tmux-level failures come back as `Response::Error`.
+    pub fn command(&mut self, args: &[&str]) -> io::Result<Response> {
+        let line = args.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" ");
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        self.wait_response()
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// The `$id` of the session this client is attached to. tmux counts the
+    /// control client in `session_attached`, so callers use this to discount it.
+    pub fn session_id(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn wait_response(&self) -> io::Result<Response> {
+        match self.responses.recv_timeout(RESPONSE_TIMEOUT) {
+            Ok(r) => Ok(r),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "tmux control client timed out",
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "tmux control client exited",
+            )),
+        }
+    }
+}
+
+impl Drop for ControlClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn read_loop(
+    mut reader: impl BufRead,
+    tx: Sender<Response>,
+    session_id: Arc<Mutex<Option<String>>>,
+    notify: Option<&(Sender<(Socket, Notification)>, Socket)>,
+) {
+    let mut buf = Vec::new();
+    // (time and command number, collected output) while inside a %begin
+    // block. tmux closes it with the same two fields.
+    let mut block: Option<(String, String)> = None;
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if let Some((number, output)) = block.as_mut() {
+            let closing = line
+                .strip_prefix("%end ")
+                .map(|rest| (rest, false))
+                .or_else(|| line.strip_prefix("%error ").map(|rest| (rest, true)));
+            match closing {
+                Some((rest, is_error)) if block_id(rest) == number.as_str() => {
+                    let output = std::mem::take(output);
+                    block = None;
+                    let response = if is_error {
+                        Response::Error(output.trim_end().to_string())
+                    } else {
+                        Response::Output(output)
+                    };
+                    if tx.send(response).is_err() {
+                        return;
+                    }
+                }
+                _ => {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("%begin ") {
+            block = Some((block_id(rest).to_string(), String::new()));
+        } else if let Some(rest) = line.strip_prefix("%session-changed ") {
+            if let Ok(mut id) = session_id.lock() {
+                *id = rest.split(' ').next().map(str::to_string);
+            }
+        } else if line.starts_with("%exit") {
+            return;
+        }
+
+        if let (Some((notify, socket)), Some(n)) = (notify, parse_notification(line)) {
+            let _ = notify.send((socket.clone(), n));
+        }
+    }
+}
+
+/// The time and command number that open a `%begin` line's fields, e.g.
+/// "1363006971 2" of "1363006971 2 1".
+fn block_id(fields: &str) -> &str {
+    match fields.match_indices(' ').nth(1) {
+        Some((end, _)) => &fields[..end],
+        None => fields,
+    }
+}
+
+fn parse_notification(line: &str) -> Option<Notification> {
+    let mut parts = line.splitn(3, ' ');
+    let name = parts.next()?;
+    let arg = parts.next().unwrap_or_default().to_string();
+    match name {
+        "%sessions-changed"
+        | "%session-renamed"
+        | "%client-session-changed"
+        | "%client-detached" => Some(Notification::SessionsChanged),
+        "%window-add"
+        | "%window-close"
+        | "%window-renamed"
+        | "%unlinked-window-add"
+        | "%unlinked-window-close"
+        | "%unlinked-window-renamed"
+        | "%session-window-changed" => Some(Notification::WindowsChanged),
+        "%layout-change" | "%window-pane-changed" => Some(Notification::LayoutChanged(arg)),
+        "%output" => Some(Notification::Output(arg)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_end_only_on_their_own_time_and_number() {
+        // The pane printed a line that looks like the end of the block.
+        let stream = "%begin 1700000000 7 1\n%end 1600000000 7 1\nok\n%end 1700000000 7 1\n";
+        let (tx, rx) = mpsc::channel();
+        read_loop(stream.as_bytes(), tx, Arc::new(Mutex::new(None)), None);
+        match rx.recv().unwrap() {
+            Response::Output(out) => assert_eq!(out, "%end 1600000000 7 1\nok\n"),
+            Response::Error(e) => panic!("unexpected error: {}", e),
+        }
+    }
+}
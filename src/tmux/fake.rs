@@ -0,0 +1,584 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::{Result, eyre};
+
+use crate::layout::{LayoutKind, LayoutNode, PRESETS};
+use crate::model::{TmuxPane, TmuxSession, TmuxWindow};
+
+use super::{Socket, TmuxBackend};
+
+const WINDOW_WIDTH: u32 = 80;
+const WINDOW_HEIGHT: u32 = 24;
+const DEFAULT_CWD: &str = "/";
+
+#[derive(Debug, Clone)]
+struct FakePane {
+    id: String,
+    active: bool,
+    cwd: String,
+    command: String,
+    width: u32,
+    height: u32,
+    top: u32,
+    left: u32,
+    content: String,
+}
+
+#[derive(Debug, Clone)]
+struct FakeWindow {
+    id: String,
+    index: u32,
+    name: String,
+    active: bool,
+    zoomed: bool,
+    /// Last custom layout applied, with this window's pane ids; forgotten
+    /// when panes change.
+    layout: String,
+    panes: Vec<FakePane>,
+    options: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct FakeSession {
+    id: String,
+    name: String,
+    windows: Vec<FakeWindow>,
+    options: BTreeMap<String, String>,
+    environment: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    sessions: Vec<FakeSession>,
+    next_session_id: u32,
+    next_window_id: u32,
+    next_pane_id: u32,
+    /// The global `base-index`, which new sessions number windows from.
+    base_index: u32,
+}
+
+impl FakeState {
+    fn new_pane(&mut self, cwd: &str) -> FakePane {
+        let id = format!("%{}", self.next_pane_id);
+        self.next_pane_id += 1;
+        FakePane {
+            id,
+            active: true,
+            cwd: cwd.to_string(),
+            command: "bash".to_string(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            top: 0,
+            left: 0,
+            content: String::new(),
+        }
+    }
+
+    fn new_window(&mut self, index: u32, name: &str, cwd: &str) -> FakeWindow {
+        let id = format!("@{}", self.next_window_id);
+        self.next_window_id += 1;
+        let pane = self.new_pane(cwd);
+        FakeWindow {
+            id,
+            index,
+            name: if name.is_empty() { "bash".to_string() } else { name.to_string() },
+            active: true,
+            zoomed: false,
+            layout: String::new(),
+            panes: vec![pane],
+            options: BTreeMap::new(),
+        }
+    }
+
+    fn session(&mut self, name: &str) -> Result<&mut FakeSession> {
+        self.sessions
+            .iter_mut()
+            .find(|s| s.name == name)
+            .ok_or_else(|| eyre!("can't find session: {}", name))
+    }
+
+    fn window(&mut self, session: &str, index: u32) -> Result<&mut FakeWindow> {
+        self.session(session)?
+            .windows
+            .iter_mut()
+            .find(|w| w.index == index)
+            .ok_or_else(|| eyre!("can't find window: {}", index))
+    }
+
+    /// Locate a pane by `%id`, returning (session, window, pane) positions.
+    fn find_pane(&self, pane_id: &str) -> Result<(usize, usize, usize)> {
+        for (si, s) in self.sessions.iter().enumerate() {
+            for (wi, w) in s.windows.iter().enumerate() {
+                if let Some(pi) = w.panes.iter().position(|p| p.id == pane_id) {
+                    return Ok((si, wi, pi));
+                }
+            }
+        }
+        Err(eyre!("can't find pane: {}", pane_id))
+    }
+
+    fn add_session(&mut self, name: &str, cwd: &str, environment: &BTreeMap<String, String>) -> Result<()> {
+        if self.sessions.iter().any(|s| s.name == name) {
+            return Err(eyre!("duplicate session: {}", name));
+        }
+        let id = format!("${}", self.next_session_id);
+        self.next_session_id += 1;
+        let window = self.new_window(self.base_index, "", cwd);
+        self.sessions.push(FakeSession {
+            id,
+            name: name.to_string(),
+            windows: vec![window],
+            options: BTreeMap::new(),
+            environment: environment.clone(),
+        });
+        Ok(())
+    }
+
+    fn add_window(&mut self, session: &str, name: &str, cwd: &str, select: bool) -> Result<()> {
+        let index = self
+            .session(session)?
+            .windows
+            .iter()
+            .map(|w| w.index + 1)
+            .max()
+            .unwrap_or(0);
+        let mut window = self.new_window(index, name, cwd);
+        let s = self.session(session)?;
+        if select {
+            s.windows.iter_mut().for_each(|w| w.active = false);
+        } else {
+            window.active = false;
+        }
+        s.windows.push(window);
+        Ok(())
+    }
+
+    /// Split `pane_id` in two. `-h` places the new pane to the right, `-v`
+    /// below; the new pane becomes active, as in tmux.
+    fn split(&mut self, pane_id: &str, direction: &str, cwd: Option<&str>) -> Result<()> {
+        let (si, wi, pi) = self.find_pane(pane_id)?;
+        let cwd = cwd
+            .map(str::to_string)
+            .unwrap_or_else(|| self.sessions[si].windows[wi].panes[pi].cwd.clone());
+        let mut new = self.new_pane(&cwd);
+
+        let window = &mut self.sessions[si].windows[wi];
+        let old = &mut window.panes[pi];
+        match direction {
+            "-h" => {
+                if old.width < 3 {
+                    return Err(eyre!("no space for new pane"));
+                }
+                let new_w = (old.width - 1) / 2;
+                old.width -= new_w + 1;
+                new.width = new_w;
+                new.height = old.height;
+                new.top = old.top;
+                new.left = old.left + old.width + 1;
+            }
+            "-v" => {
+                if old.height < 3 {
+                    return Err(eyre!("no space for new pane"));
+                }
+                let new_h = (old.height - 1) / 2;
+                old.height -= new_h + 1;
+                new.height = new_h;
+                new.width = old.width;
+                new.left = old.left;
+                new.top = old.top + old.height + 1;
+            }
+            other => return Err(eyre!("unknown split direction: {}", other)),
+        }
+
+        window.panes.iter_mut().for_each(|p| p.active = false);
+        window.panes.insert(pi + 1, new);
+        window.layout.clear();
+        Ok(())
+    }
+
+    /// Drop windows without panes and sessions without windows.
+    fn prune(&mut self) {
+        for s in &mut self.sessions {
+            s.windows.retain(|w| !w.panes.is_empty());
+            for w in &mut s.windows {
+                if !w.panes.iter().any(|p| p.active) {
+                    w.panes[0].active = true;
+                }
+            }
+            if !s.windows.is_empty() && !s.windows.iter().any(|w| w.active) {
+                s.windows[0].active = true;
+            }
+        }
+        self.sessions.retain(|s| !s.windows.is_empty());
+    }
+}
+
+/// An in-memory tmux server. Sessions, windows and panes behave like tmux's
+/// (ids, indexes, active flags, split geometry) closely enough to drive `App`
+/// and template launching without a real server.
+#[derive(Default)]
+pub struct FakeServer {
+    socket: Socket,
+    state: RefCell<FakeState>,
+    /// Directories that `directory_exists` denies; all others exist.
+    missing_dirs: RefCell<Vec<String>>,
+}
+
+impl FakeServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A server that reports `socket`, for tests with several servers.
+    pub fn with_socket(socket: Socket) -> Self {
+        Self {
+            socket,
+            ..Self::default()
+        }
+    }
+
+    /// Set what `capture_pane` returns for `pane_id`.
+    pub fn set_pane_content(&self, pane_id: &str, content: &str) {
+        let mut state = self.state.borrow_mut();
+        if let Ok((si, wi, pi)) = state.find_pane(pane_id) {
+            state.sessions[si].windows[wi].panes[pi].content = content.to_string();
+        }
+    }
+
+    /// Set the global `base-index`, as a `~/.tmux.conf` might.
+    pub fn set_base_index(&self, base: u32) {
+        self.state.borrow_mut().base_index = base;
+    }
+
+    /// Make `directory_exists` report `path` as missing.
+    pub fn remove_dir(&self, path: &str) {
+        self.missing_dirs.borrow_mut().push(path.to_string());
+    }
+}
+
+impl TmuxBackend for FakeServer {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>> {
+        Ok(self
+            .state
+            .borrow()
+            .sessions
+            .iter()
+            .map(|s| TmuxSession {
+                server: self.socket.clone(),
+                name: s.name.clone(),
+                id: s.id.clone(),
+                windows: s.windows.len() as u32,
+                attached: false,
+                created: 0,
+                last_activity: 0,
+            })
+            .collect())
+    }
+
+    fn list_windows(&self, session: &str) -> Result<Vec<TmuxWindow>> {
+        let mut state = self.state.borrow_mut();
+        Ok(state
+            .session(session)?
+            .windows
+            .iter()
+            .map(|w| TmuxWindow {
+                name: w.name.clone(),
+                index: w.index,
+                id: w.id.clone(),
+                active: w.active,
+                panes: w.panes.len() as u32,
+                layout: w.layout.clone(),
+                flags: format!(
+                    "{}{}",
+                    if w.active { "*" } else { "" },
+                    if w.zoomed { "Z" } else { "" }
+                ),
+            })
+            .collect())
+    }
+
+    fn list_panes(&self, session: &str, window_index: u32) -> Result<Vec<TmuxPane>> {
+        let mut state = self.state.borrow_mut();
+        Ok(state
+            .window(session, window_index)?
+            .panes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| TmuxPane {
+                id: p.id.clone(),
+                index: i as u32,
+                active: p.active,
+                command: p.command.clone(),
+                width: p.width,
+                height: p.height,
+                top: p.top,
+                left: p.left,
+                cwd: p.cwd.clone(),
+                pid: 0,
+                title: String::new(),
+            })
+            .collect())
+    }
+
+    fn capture_pane(&self, pane_id: &str) -> Result<String> {
+        let state = self.state.borrow();
+        let (si, wi, pi) = state.find_pane(pane_id)?;
+        Ok(state.sessions[si].windows[wi].panes[pi].content.clone())
+    }
+
+    /// All of a fake pane's content counts as history, so a depth keeps just
+    /// its last lines.
+    fn capture_scrollback(&self, pane_id: &str, depth: Option<usize>, _escapes: bool) -> Result<String> {
+        let content = self.capture_pane(pane_id)?;
+        let Some(depth) = depth else {
+            return Ok(content);
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        Ok(lines[lines.len().saturating_sub(depth)..].iter().map(|l| format!("{}\n", l)).collect())
+    }
+
+    fn copy_mode_at(&self, pane_id: &str, _line: usize) -> Result<()> {
+        self.state.borrow().find_pane(pane_id)?;
+        Ok(())
+    }
+
+    fn new_session(&self, name: &str) -> Result<()> {
+        self.state.borrow_mut().add_session(name, DEFAULT_CWD, &BTreeMap::new())
+    }
+
+    fn new_session_with_cwd(&self, name: &str, cwd: &str, environment: &BTreeMap<String, String>) -> Result<()> {
+        self.state.borrow_mut().add_session(name, cwd, environment)
+    }
+
+    fn kill_session(&self, name: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.session(name)?;
+        state.sessions.retain(|s| s.name != name);
+        Ok(())
+    }
+
+    fn rename_session(&self, old: &str, new: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if state.sessions.iter().any(|s| s.name == new) {
+            return Err(eyre!("duplicate session: {}", new));
+        }
+        state.session(old)?.name = new.to_string();
+        Ok(())
+    }
+
+    fn new_window(&self, session: &str, name: Option<&str>) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .add_window(session, name.unwrap_or_default(), DEFAULT_CWD, false)
+    }
+
+    fn new_window_with_cwd(&self, session: &str, name: &str, cwd: &str) -> Result<()> {
+        self.state.borrow_mut().add_window(session, name, cwd, false)
+    }
+
+    fn kill_window(&self, session: &str, window_index: u32) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.window(session, window_index)?;
+        state
+            .session(session)?
+            .windows
+            .retain(|w| w.index != window_index);
+        state.prune();
+        Ok(())
+    }
+
+    fn rename_window(&self, session: &str, window_index: u32, new_name: &str) -> Result<()> {
+        self.state.borrow_mut().window(session, window_index)?.name = new_name.to_string();
+        Ok(())
+    }
+
+    fn select_window(&self, session: &str, window_index: u32) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.window(session, window_index)?;
+        for w in &mut state.session(session)?.windows {
+            w.active = w.index == window_index;
+        }
+        Ok(())
+    }
+
+    fn renumber_windows(&self, session: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let global = state.base_index;
+        let session = state.session(session)?;
+        let base = session.options.get("base-index").and_then(|v| v.parse().ok()).unwrap_or(global);
+        for (i, window) in session.windows.iter_mut().enumerate() {
+            window.index = base + i as u32;
+        }
+        Ok(())
+    }
+
+    fn set_option(&self, session: &str, window: Option<u32>, name: &str, value: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let options = match window {
+            Some(index) => &mut state.window(session, index)?.options,
+            None => &mut state.session(session)?.options,
+        };
+        options.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn unset_option(&self, session: &str, name: &str) -> Result<()> {
+        self.state.borrow_mut().session(session)?.options.remove(name);
+        Ok(())
+    }
+
+    fn show_options(&self, session: &str, window: Option<u32>) -> Result<BTreeMap<String, String>> {
+        let mut state = self.state.borrow_mut();
+        Ok(match window {
+            Some(index) => state.window(session, index)?.options.clone(),
+            None => state.session(session)?.options.clone(),
+        })
+    }
+
+    fn set_environment(&self, session: &str, name: &str, value: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.session(session)?.environment.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn show_environment(&self, session: &str) -> Result<BTreeMap<String, String>> {
+        Ok(self.state.borrow_mut().session(session)?.environment.clone())
+    }
+
+    fn select_layout(&self, session: &str, window_index: u32, layout: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let window = state.window(session, window_index)?;
+        // Named layouts are accepted, but the panes keep their geometry.
+        if PRESETS.contains(&layout) {
+            return Ok(());
+        }
+        let mut node = LayoutNode::parse(layout)?;
+        if node.pane_count() != window.panes.len() {
+            return Err(eyre!("invalid layout"));
+        }
+        assign_panes(&mut node, &mut window.panes.iter_mut());
+        window.layout = node.to_string();
+        Ok(())
+    }
+
+    fn split_window_in_dir(&self, session: &str, window_index: u32, direction: &str, cwd: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let active = state
+            .window(session, window_index)?
+            .panes
+            .iter()
+            .find(|p| p.active)
+            .map(|p| p.id.clone())
+            .ok_or_else(|| eyre!("no active pane"))?;
+        state.split(&active, direction, Some(cwd))
+    }
+
+    fn split_window_horizontal(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()> {
+        let _ = (session, window_index);
+        self.state.borrow_mut().split(pane_id, "-v", None)
+    }
+
+    fn split_window_vertical(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()> {
+        let _ = (session, window_index);
+        self.state.borrow_mut().split(pane_id, "-h", None)
+    }
+
+    fn kill_pane(&self, pane_id: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (si, wi, pi) = state.find_pane(pane_id)?;
+        state.sessions[si].windows[wi].panes.remove(pi);
+        state.sessions[si].windows[wi].layout.clear();
+        state.prune();
+        Ok(())
+    }
+
+    fn select_pane(&self, pane_id: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (si, wi, pi) = state.find_pane(pane_id)?;
+        for (i, p) in state.sessions[si].windows[wi].panes.iter_mut().enumerate() {
+            p.active = i == pi;
+        }
+        Ok(())
+    }
+
+    fn resize_pane_zoom(&self, pane_id: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (si, wi, _) = state.find_pane(pane_id)?;
+        let window = &mut state.sessions[si].windows[wi];
+        window.zoomed = !window.zoomed;
+        Ok(())
+    }
+
+    fn break_pane(&self, pane_id: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (si, wi, pi) = state.find_pane(pane_id)?;
+        let mut pane = state.sessions[si].windows[wi].panes.remove(pi);
+        pane.width = WINDOW_WIDTH;
+        pane.height = WINDOW_HEIGHT;
+        pane.top = 0;
+        pane.left = 0;
+        pane.active = true;
+
+        let id = format!("@{}", state.next_window_id);
+        state.next_window_id += 1;
+        let session = &mut state.sessions[si];
+        let index = session.windows.iter().map(|w| w.index + 1).max().unwrap_or(0);
+        session.windows.push(FakeWindow {
+            id,
+            index,
+            name: pane.command.clone(),
+            active: false,
+            zoomed: false,
+            layout: String::new(),
+            panes: vec![pane],
+            options: BTreeMap::new(),
+        });
+        state.prune();
+        Ok(())
+    }
+
+    fn send_keys(&self, pane_id: &str, line: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (si, wi, pi) = state.find_pane(pane_id)?;
+        let content = &mut state.sessions[si].windows[wi].panes[pi].content;
+        content.push_str(line);
+        content.push('\n');
+        Ok(())
+    }
+
+    fn switch_client(&self, target: &str) -> Result<()> {
+        let session = target.split(':').next().unwrap_or_default();
+        self.state.borrow_mut().session(session)?;
+        Ok(())
+    }
+
+    fn socket(&self) -> Socket {
+        self.socket.clone()
+    }
+
+    fn directory_exists(&self, path: &str) -> bool {
+        !self.missing_dirs.borrow().iter().any(|d| d == path)
+    }
+}
+
+/// Give each layout cell's geometry to the next pane, and record that pane's
+/// id in the cell as tmux does.
+fn assign_panes<'a>(node: &mut LayoutNode, panes: &mut impl Iterator<Item = &'a mut FakePane>) {
+    match &mut node.kind {
+        LayoutKind::Pane(id) => {
+            if let Some(pane) = panes.next() {
+                pane.left = node.x;
+                pane.top = node.y;
+                pane.width = node.width;
+                pane.height = node.height;
+                *id = pane.id[1..].parse().ok();
+            }
+        }
+        LayoutKind::Horizontal(children) | LayoutKind::Vertical(children) => {
+            for child in children {
+                assign_panes(child, panes);
+            }
+        }
+    }
+}
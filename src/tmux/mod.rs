@@ -0,0 +1,269 @@
+mod client;
+mod control;
+#[cfg(test)]
+pub mod fake;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::eyre::{Result, eyre};
+
+use crate::model::{PaneEntry, TmuxPane, TmuxSession, TmuxWindow};
+
+pub use client::TmuxClient;
+pub use control::Notification;
+
+/// Which tmux server to talk to, mirroring tmux's own `-L` / `-S` flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Socket {
+    /// Whatever plain `tmux` would use: `$TMUX`'s server, else "default".
+    #[default]
+    Default,
+    Name(String),
+    Path(PathBuf),
+}
+
+impl Socket {
+    /// `TMMX_SOCKET_PATH` / `TMMX_SOCKET_NAME`, path taking precedence.
+    pub fn from_env() -> Self {
+        if let Some(path) = std::env::var_os("TMMX_SOCKET_PATH").filter(|v| !v.is_empty()) {
+            Socket::Path(PathBuf::from(path))
+        } else if let Some(name) = std::env::var("TMMX_SOCKET_NAME").ok().filter(|v| !v.is_empty()) {
+            Socket::Name(name)
+        } else {
+            Socket::Default
+        }
+    }
+
+    /// Normalise to the form `discover_sockets` produces, so one server isn't
+    /// listed twice under different spellings.
+    pub fn canonical(&self) -> Socket {
+        match self {
+            Socket::Default => match current_socket_path() {
+                Some(path) => Socket::Path(path).canonical(),
+                None => Socket::Name("default".to_string()),
+            },
+            Socket::Path(path) if path.parent() == Some(socket_dir().as_path()) => path
+                .file_name()
+                .map(|n| Socket::Name(n.to_string_lossy().to_string()))
+                .unwrap_or_else(|| self.clone()),
+            other => other.clone(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Socket::Default => "default".to_string(),
+            Socket::Name(name) => name.clone(),
+            Socket::Path(path) => path.display().to_string(),
+        }
+    }
+
+    /// A `tmux` command pre-loaded with this socket's flags.
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new("tmux");
+        match self {
+            Socket::Default => {}
+            Socket::Name(name) => {
+                cmd.args(["-L", name]);
+            }
+            Socket::Path(path) => {
+                cmd.arg("-S").arg(path);
+            }
+        }
+        cmd
+    }
+
+    /// The same invocation as `command`, as a shell command line.
+    pub fn shell_command(&self) -> String {
+        match self {
+            Socket::Default => "tmux".to_string(),
+            Socket::Name(name) => format!("tmux -L {}", quote(name)),
+            Socket::Path(path) => format!("tmux -S {}", quote(&path.to_string_lossy())),
+        }
+    }
+}
+
+/// Directory tmux creates its sockets in: `$TMUX_TMPDIR/tmux-$UID`.
+pub fn socket_dir() -> PathBuf {
+    let base = std::env::var_os("TMUX_TMPDIR")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    base.join(format!("tmux-{}", uid))
+}
+
+/// Every server socket in `socket_dir`, by name. Stale sockets are included;
+/// they simply fail to list any sessions.
+pub fn discover_sockets() -> Vec<Socket> {
+    let mut sockets: Vec<Socket> = fs::read_dir(socket_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_socket()))
+        .map(|e| Socket::Name(e.file_name().to_string_lossy().to_string()))
+        .collect();
+    sockets.sort_by_key(|s| s.label());
+    sockets
+}
+
+/// Socket of the server tmmx is running inside, from `$TMUX`.
+fn current_socket_path() -> Option<PathBuf> {
+    let env = std::env::var("TMUX").ok().filter(|v| !v.is_empty())?;
+    env.split(',').next().map(PathBuf::from)
+}
+
+/// Quote an argument for the tmux command parser or a POSIX shell. Single
+/// quotes disable all expansion in both, so only embedded single quotes need
+/// escaping.
+pub fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Operations tmmx performs against a tmux server. `TmuxClient` talks to a real
+/// server; `fake::FakeServer` models one in memory for tests.
+pub trait TmuxBackend {
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>>;
+    fn list_windows(&self, session: &str) -> Result<Vec<TmuxWindow>>;
+    fn list_panes(&self, session: &str, window_index: u32) -> Result<Vec<TmuxPane>>;
+    fn capture_pane(&self, pane_id: &str) -> Result<String>;
+    /// The last `depth` lines of the pane's history, or all of it without a
+    /// depth, followed by its visible lines, oldest first. With `escapes`,
+    /// colours and attributes are kept as SGR escape sequences.
+    fn capture_scrollback(&self, pane_id: &str, depth: Option<usize>, escapes: bool) -> Result<String>;
+    /// Put the pane in copy mode with the cursor on `line` of what
+    /// `capture_scrollback` returned.
+    fn copy_mode_at(&self, pane_id: &str, line: usize) -> Result<()>;
+
+    /// Every pane on the server, in session and window order.
+    fn list_all_panes(&self) -> Result<Vec<PaneEntry>> {
+        let mut entries = Vec::new();
+        for session in self.list_sessions()? {
+            for window in self.list_windows(&session.name)? {
+                for pane in self.list_panes(&session.name, window.index)? {
+                    entries.push(PaneEntry {
+                        session: session.name.clone(),
+                        window_index: window.index,
+                        window_name: window.name.clone(),
+                        pane,
+                    });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn new_session(&self, name: &str) -> Result<()>;
+    /// Create a detached session whose panes start in `cwd`, with `environment`
+    /// set in the session's environment, first pane included.
+    fn new_session_with_cwd(&self, name: &str, cwd: &str, environment: &BTreeMap<String, String>) -> Result<()>;
+    fn kill_session(&self, name: &str) -> Result<()>;
+    fn rename_session(&self, old: &str, new: &str) -> Result<()>;
+
+    fn new_window(&self, session: &str, name: Option<&str>) -> Result<()>;
+    fn new_window_with_cwd(&self, session: &str, name: &str, cwd: &str) -> Result<()>;
+    fn kill_window(&self, session: &str, window_index: u32) -> Result<()>;
+    fn rename_window(&self, session: &str, window_index: u32, new_name: &str) -> Result<()>;
+    fn select_window(&self, session: &str, window_index: u32) -> Result<()>;
+    fn select_layout(&self, session: &str, window_index: u32, layout: &str) -> Result<()>;
+    /// Renumber the session's windows from its `base-index`.
+    fn renumber_windows(&self, session: &str) -> Result<()>;
+
+    /// Set an option on the session, or with `window` on that window.
+    fn set_option(&self, session: &str, window: Option<u32>, name: &str, value: &str) -> Result<()>;
+    /// Options set on the session, or with `window` on that window, rather
+    /// than inherited from the global ones.
+    fn show_options(&self, session: &str, window: Option<u32>) -> Result<BTreeMap<String, String>>;
+    /// Drop an option set on the session, so the global value applies.
+    fn unset_option(&self, session: &str, name: &str) -> Result<()>;
+    fn set_environment(&self, session: &str, name: &str, value: &str) -> Result<()>;
+    /// The session's environment, less what tmux copies from the client that
+    /// created it (`update-environment`).
+    fn show_environment(&self, session: &str) -> Result<BTreeMap<String, String>>;
+
+    fn split_window_in_dir(&self, session: &str, window_index: u32, direction: &str, cwd: &str) -> Result<()>;
+    fn split_window_horizontal(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()>;
+    fn split_window_vertical(&self, session: &str, window_index: u32, pane_id: &str) -> Result<()>;
+    fn kill_pane(&self, pane_id: &str) -> Result<()>;
+    fn select_pane(&self, pane_id: &str) -> Result<()>;
+    fn resize_pane_zoom(&self, pane_id: &str) -> Result<()>;
+    fn break_pane(&self, pane_id: &str) -> Result<()>;
+    /// Type `line` into the pane, followed by Enter.
+    fn send_keys(&self, pane_id: &str, line: &str) -> Result<()>;
+
+    fn switch_client(&self, target: &str) -> Result<()>;
+
+    fn socket(&self) -> Socket {
+        Socket::Default
+    }
+
+    /// Whether new panes can start in `path`. tmux doesn't check: a pane
+    /// given a missing directory quietly starts in the home directory.
+    fn directory_exists(&self, path: &str) -> bool {
+        Path::new(path).is_dir()
+    }
+
+    /// Whether tmmx is running inside a client of this server, so
+    /// `switch_client` can move it instead of attaching from outside.
+    fn contains_current_client(&self) -> bool {
+        false
+    }
+
+    /// Hint that the UI is looking at `session`, so change notifications for
+    /// it should be delivered.
+    fn follow_session(&self, _session: &str) {}
+
+    /// Hint that the preview shows `pane_id`, so output from the followed
+    /// session's other panes needn't be delivered.
+    fn follow_pane(&self, _pane_id: &str) {}
+
+    /// Whether state changes arrive as notifications, making polling unnecessary.
+    fn is_control_connected(&self) -> bool {
+        false
+    }
+}
+
+pub fn is_tmux_running(socket: &Socket) -> bool {
+    socket
+        .command()
+        .args(["list-sessions"])
+        .output()
+        .map(|o| o.status.success() || o.status.code() == Some(1))
+        .unwrap_or(false)
+}
+
+pub fn is_inside_tmux() -> bool {
+    std::env::var("TMUX")
+        .map(|v| !v.is_empty())
+        .unwrap_or(false)
+}
+
+/// Attach the current terminal to `target`. Inherits stdio, so this must run
+/// after the TUI has restored the terminal.
+pub fn attach_session(socket: &Socket, target: &str) -> Result<()> {
+    socket
+        .command()
+        .args(["attach-session", "-t", target])
+        .status()?;
+    Ok(())
+}
+
+/// Move the client tmmx is running in over to `target` on another server.
+/// `switch-client` can't cross servers, so the client is detached and its
+/// process replaced with an attach to the other server.
+pub fn switch_server(socket: &Socket, target: &str) -> Result<()> {
+    let attach = format!("{} attach-session -t {}", socket.shell_command(), quote(target));
+    let output = Command::new("tmux")
+        .args(["detach-client", "-E", &attach])
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("{}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
@@ -1,10 +1,10 @@
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 
 use crate::app::{App, Panel};
+use super::centered_rect;
 
 pub fn draw_help(frame: &mut Frame, app: &App) {
     let area = centered_rect(60, 70, frame.area());
@@ -26,12 +26,16 @@ pub fn draw_help(frame: &mut Frame, app: &App) {
         key_line("R", "Force refresh"),
         key_line("1-4", "Switch panel"),
         key_line("Tab/S-Tab", "Next/prev panel"),
+        key_line("/", "Filter list"),
+        key_line("Esc", "Clear filter"),
+        key_line("f", "Jump to any pane"),
+        key_line("F", "Search scrollback"),
         Line::from(""),
+        section_header(app.focused.label()),
     ];
 
     let panel_lines = match app.focused {
         Panel::Sessions => vec![
-            section_header("Sessions"),
             key_line("j/k ↑/↓", "Navigate"),
             key_line("h/l ←/→", "Switch panel"),
             key_line("n", "New session"),
@@ -39,10 +43,10 @@ pub fn draw_help(frame: &mut Frame, app: &App) {
             key_line("d", "Kill session"),
             key_line("Enter", "Switch to session"),
             key_line("S", "Save as template"),
+            key_line("x", "Export session"),
             key_line("t", "Open templates"),
         ],
         Panel::Windows => vec![
-            section_header("Windows"),
             key_line("j/k ↑/↓", "Navigate"),
             key_line("h/l ←/→", "Switch panel"),
             key_line("n", "New window"),
@@ -51,7 +55,6 @@ pub fn draw_help(frame: &mut Frame, app: &App) {
             key_line("Enter", "Switch to window"),
         ],
         Panel::Panes => vec![
-            section_header("Panes"),
             key_line("j/k ↑/↓", "Navigate"),
             key_line("h/l ←/→", "Switch panel"),
             key_line("n", "Split vertical"),
@@ -62,7 +65,9 @@ pub fn draw_help(frame: &mut Frame, app: &App) {
             key_line("Enter", "Switch to pane"),
         ],
         Panel::Preview => vec![
-            section_header("Preview"),
+            key_line("j/k ↑/↓", "Scroll"),
+            key_line("PgUp/PgDn", "Scroll a page"),
+            key_line("g/G", "Start/end of history"),
             key_line("h/l ←/→", "Switch panel"),
         ],
     };
@@ -91,23 +96,3 @@ fn key_line(key: &str, desc: &str) -> Line<'static> {
         Span::raw(desc.to_string()),
     ])
 }
-
-fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
-    let vertical = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(area);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(vertical[1])[1]
-}
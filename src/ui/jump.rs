@@ -0,0 +1,77 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, Paragraph};
+
+use crate::app::App;
+use super::{centered_rect, highlight_matches};
+
+pub fn draw_jump(frame: &mut Frame, app: &mut App) {
+    let area = centered_rect(70, 60, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Jump to ")
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}_", app.jump_query)),
+        ])),
+        rows[0],
+    );
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "type to search  Up/Down:move  Enter:jump  Esc:close",
+            Style::default().fg(Color::DarkGray),
+        ))),
+        rows[2],
+    );
+
+    let matches = app.jump_matches();
+    if matches.is_empty() {
+        let msg = if app.jump_targets.is_empty() { "(no panes)" } else { "(no matches)" };
+        let msg = Paragraph::new(Line::from(Span::styled(msg, Style::default().fg(Color::DarkGray))));
+        frame.render_widget(msg, rows[1]);
+        return;
+    }
+
+    // With several servers, say which one each pane is on.
+    let several = app.servers.len() > 1;
+    let items: Vec<Line> = matches
+        .iter()
+        .map(|(i, matched)| {
+            let target = &app.jump_targets[*i];
+            let mut spans = highlight_matches(&target.label(), matched);
+            if several {
+                spans.push(Span::styled(
+                    format!("  @{}", target.server.label()),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    frame.render_stateful_widget(list, rows[1], &mut app.jump_state);
+}
@@ -4,11 +4,21 @@ use ratatui::layout::{Position, Rect};
 use ratatui::style::{Color, Style};
 
 use crate::app::App;
+use crate::layout::{LayoutKind, LayoutNode};
 use crate::model::TmuxPane;
 
 const MIN_CELL_WIDTH: u16 = 3;
 const MIN_CELL_HEIGHT: u16 = 2;
 
+/// A pane's rectangle in window cells.
+struct Cell<'a> {
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    pane: &'a TmuxPane,
+}
+
 struct MappedPane {
     x: u16,
     y: u16,
@@ -31,8 +41,11 @@ pub fn draw_layout_minimap(frame: &mut Frame, app: &App, area: Rect) -> bool {
         return true;
     }
 
-    let selected_index = app.pane_state.selected();
-    let mapped = map_panes(&app.panes, area, selected_index);
+    let selected_id = app.selected_pane().map(|p| p.id);
+    let layout = app.selected_window().map(|w| w.layout).unwrap_or_default();
+    let (win_w, win_h, cells) =
+        layout_cells(&layout, &app.panes).unwrap_or_else(|| offset_cells(&app.panes));
+    let mapped = map_panes(&cells, win_w, win_h, area, selected_id.as_deref());
 
     // Check if area is too small
     if mapped.is_none() {
@@ -49,30 +62,77 @@ pub fn draw_layout_minimap(frame: &mut Frame, app: &App, area: Rect) -> bool {
     true
 }
 
-fn map_panes(panes: &[TmuxPane], area: Rect, selected_index: Option<usize>) -> Option<Vec<MappedPane>> {
-    // Derive window total dimensions
+/// Cells from the window's layout tree, which keeps the real geometry while a
+/// pane is zoomed. `None` if the layout doesn't parse or doesn't match `panes`.
+fn layout_cells<'a>(layout: &str, panes: &'a [TmuxPane]) -> Option<(u32, u32, Vec<Cell<'a>>)> {
+    let root = LayoutNode::parse(layout).ok()?;
+    let leaves = root.panes();
+    if leaves.len() != panes.len() {
+        return None;
+    }
+    let cells = leaves
+        .into_iter()
+        .map(|leaf| {
+            let LayoutKind::Pane(Some(id)) = leaf.kind else {
+                return None;
+            };
+            let pane = panes.iter().find(|p| p.id == format!("%{}", id))?;
+            Some(Cell {
+                left: leaf.x,
+                top: leaf.y,
+                width: leaf.width,
+                height: leaf.height,
+                pane,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((root.width, root.height, cells))
+}
+
+/// Cells from the panes' own offsets, sizing the window to fit them.
+fn offset_cells(panes: &[TmuxPane]) -> (u32, u32, Vec<Cell<'_>>) {
     let win_w = panes.iter().map(|p| p.left + p.width).max().unwrap_or(1);
     let win_h = panes.iter().map(|p| p.top + p.height).max().unwrap_or(1);
+    let cells = panes
+        .iter()
+        .map(|pane| Cell {
+            left: pane.left,
+            top: pane.top,
+            width: pane.width,
+            height: pane.height,
+            pane,
+        })
+        .collect();
+    (win_w, win_h, cells)
+}
 
+fn map_panes(
+    cells: &[Cell],
+    win_w: u32,
+    win_h: u32,
+    area: Rect,
+    selected_id: Option<&str>,
+) -> Option<Vec<MappedPane>> {
     if win_w == 0 || win_h == 0 {
         return Some(Vec::new());
     }
 
     // Check if we have enough space for all panes at minimum size
-    let needed_w = (panes.len() as u16) * MIN_CELL_WIDTH;
+    let needed_w = (cells.len() as u16) * MIN_CELL_WIDTH;
     let needed_h = MIN_CELL_HEIGHT;
     if area.width < needed_w.min(MIN_CELL_WIDTH) || area.height < needed_h {
         return None;
     }
 
-    let mut mapped = Vec::with_capacity(panes.len());
+    let mut mapped = Vec::with_capacity(cells.len());
 
-    for (i, pane) in panes.iter().enumerate() {
-        let x = area.x + ((pane.left as u32 * area.width as u32) / win_w as u32) as u16;
-        let y = area.y + ((pane.top as u32 * area.height as u32) / win_h as u32) as u16;
+    for cell in cells {
+        let pane = cell.pane;
+        let x = area.x + ((cell.left * area.width as u32) / win_w) as u16;
+        let y = area.y + ((cell.top * area.height as u32) / win_h) as u16;
 
-        let right = area.x + (((pane.left + pane.width) as u32 * area.width as u32) / win_w as u32) as u16;
-        let bottom = area.y + (((pane.top + pane.height) as u32 * area.height as u32) / win_h as u32) as u16;
+        let right = area.x + (((cell.left + cell.width) * area.width as u32) / win_w) as u16;
+        let bottom = area.y + (((cell.top + cell.height) * area.height as u32) / win_h) as u16;
 
         let w = right.saturating_sub(x).max(MIN_CELL_WIDTH);
         let h = bottom.saturating_sub(y).max(MIN_CELL_HEIGHT);
@@ -90,7 +150,7 @@ fn map_panes(panes: &[TmuxPane], area: Rect, selected_index: Option<usize>) -> O
             w,
             h,
             label,
-            selected: selected_index == Some(i),
+            selected: selected_id == Some(pane.id.as_str()),
         });
     }
 
@@ -179,10 +239,11 @@ fn draw_pane_cell(buf: &mut Buffer, mp: &MappedPane, clip: Rect) {
 
             for (i, ch) in display.chars().enumerate() {
                 let cx = label_x + i as u16;
-                if cx < clip_right && cx < x2 {
-                    if let Some(cell) = buf.cell_mut(Position::new(cx, label_y)) {
-                        cell.set_char(ch).set_style(label_style);
-                    }
+                if cx < clip_right
+                    && cx < x2
+                    && let Some(cell) = buf.cell_mut(Position::new(cx, label_y))
+                {
+                    cell.set_char(ch).set_style(label_style);
                 }
             }
         }
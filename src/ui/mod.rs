@@ -1,17 +1,19 @@
 mod help;
+mod jump;
 mod layout_minimap;
 mod panes;
 mod preview;
 mod prompt;
+mod search;
 mod sessions;
 mod template_picker;
 mod windows;
 
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Style};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::widgets::{Block, Borders, ListState, Paragraph};
 
 use crate::app::{App, InputMode, Panel};
 
@@ -49,6 +51,12 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     if app.mode == InputMode::TemplatePicker {
         template_picker::draw_template_picker(frame, app);
     }
+    if app.mode == InputMode::Jump {
+        jump::draw_jump(frame, app);
+    }
+    if app.mode == InputMode::Search {
+        search::draw_search(frame, app);
+    }
     if app.mode == InputMode::Help {
         help::draw_help(frame, app);
     }
@@ -97,6 +105,12 @@ fn draw_narrow_layout(frame: &mut Frame, app: &mut App, area: Rect) {
     }
 }
 
+/// Capture rows the preview has room for in a terminal `height` rows tall.
+/// In either layout it takes all of the height but the status bar.
+pub fn preview_rows(height: u16) -> usize {
+    preview::capture_rows(height.saturating_sub(1))
+}
+
 pub fn panel_block(title: &str, focused: bool) -> Block<'_> {
     let border_style = if focused {
         Style::default().fg(Color::Cyan)
@@ -109,7 +123,77 @@ pub fn panel_block(title: &str, focused: bool) -> Block<'_> {
         .border_style(border_style)
 }
 
+/// A list panel's title, followed by its filter query while it has one.
+pub fn list_title(app: &App, panel: Panel, title: &str) -> String {
+    match &app.filter {
+        Some(filter) if filter.panel == panel => {
+            let cursor = if app.mode == InputMode::Filter { "_" } else { "" };
+            format!("{} /{}{}", title, filter.query, cursor)
+        }
+        _ => title.to_string(),
+    }
+}
+
+/// The middle `percent_x` by `percent_y` of `area`, for an overlay.
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// A state for rendering a list whose display rows show the items in
+/// `rows`, `None` for a row such as a header: `state` with its selected item
+/// mapped to the row showing it, and its scroll offset.
+pub fn filtered_state(state: &ListState, rows: &[Option<usize>]) -> ListState {
+    let selected = state.selected().and_then(|selected| rows.iter().position(|row| *row == Some(selected)));
+    ListState::default().with_selected(selected).with_offset(state.offset())
+}
+
+/// `text` with the characters a filter matched picked out.
+pub fn highlight_matches(text: &str, matched: &[usize]) -> Vec<Span<'static>> {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_matched = matched.contains(&i);
+        if is_matched != run_matched && !run.is_empty() {
+            let part = std::mem::take(&mut run);
+            spans.push(if run_matched { Span::styled(part, style) } else { Span::raw(part) });
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched { Span::styled(run, style) } else { Span::raw(run) });
+    }
+    spans
+}
+
 fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
+    let server = format!(" server: {} ", app.tmux().socket().label());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(server.chars().count() as u16),
+        ])
+        .split(area);
+
     let widget = if let Some(ref status) = app.status {
         let color = if status.is_error {
             Color::Red
@@ -120,11 +204,23 @@ fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
             &status.text,
             Style::default().fg(color),
         )))
+    } else if app.mode == InputMode::Filter {
+        Paragraph::new(Line::from(Span::styled(
+            " type to filter  Up/Down:move  Enter:keep filter  Esc:clear",
+            Style::default().fg(Color::DarkGray),
+        )))
     } else {
         Paragraph::new(Line::from(Span::styled(
-            " q:quit  ?:help  1-4:panels  n:new  r:rename  d:delete  Enter:switch  R:refresh",
+            " q:quit  ?:help  1-4:panels  n:new  r:rename  d:delete  Enter:switch  /:filter  f:jump  R:refresh",
             Style::default().fg(Color::DarkGray),
         )))
     };
-    frame.render_widget(widget, area);
+    frame.render_widget(widget, columns[0]);
+
+    let server_widget = Paragraph::new(Line::from(Span::styled(
+        server,
+        Style::default().fg(Color::Cyan),
+    )))
+    .alignment(Alignment::Right);
+    frame.render_widget(server_widget, columns[1]);
 }
@@ -5,11 +5,12 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::List;
 
 use crate::app::{App, Panel};
-use super::panel_block;
+use super::{filtered_state, highlight_matches, list_title, panel_block};
 
 pub fn draw_panes(frame: &mut Frame, app: &mut App, area: Rect) {
     let focused = app.focused == Panel::Panes;
-    let block = panel_block("[3] Panes", focused);
+    let title = list_title(app, Panel::Panes, "[3] Panes");
+    let block = panel_block(&title, focused);
 
     if app.panes.is_empty() {
         let msg = if app.windows.is_empty() {
@@ -25,16 +26,26 @@ pub fn draw_panes(frame: &mut Frame, app: &mut App, area: Rect) {
         frame.render_widget(list, area);
         return;
     }
+    let visible = app.visible_items(&Panel::Panes);
+    if visible.is_empty() {
+        let list = List::new(vec![Line::from(Span::styled(
+            "(no matches)",
+            Style::default().fg(Color::DarkGray),
+        ))])
+        .block(block);
+        frame.render_widget(list, area);
+        return;
+    }
 
-    let items: Vec<Line> = app
-        .panes
+    let items: Vec<Line> = visible
         .iter()
-        .map(|p| {
+        .map(|(i, matched)| {
+            let p = &app.panes[*i];
             let active = if p.active { "*" } else { " " };
-            Line::from(format!(
-                "{}{} {} ({}x{})",
-                active, p.index, p.command, p.width, p.height
-            ))
+            let mut spans = vec![Span::raw(format!("{}{} ", active, p.index))];
+            spans.extend(highlight_matches(&p.command, matched));
+            spans.push(Span::raw(format!(" ({}x{})", p.width, p.height)));
+            Line::from(spans)
         })
         .collect();
 
@@ -56,5 +67,9 @@ pub fn draw_panes(frame: &mut Frame, app: &mut App, area: Rect) {
         list = list.style(Style::default().fg(Color::DarkGray));
     }
 
-    frame.render_stateful_widget(list, area, &mut app.pane_state);
+    // The filter shifts the rows, so the selection is mapped to display rows.
+    let rows: Vec<_> = visible.iter().map(|(i, _)| Some(*i)).collect();
+    let mut state = filtered_state(&app.pane_state, &rows);
+    frame.render_stateful_widget(list, area, &mut state);
+    *app.pane_state.offset_mut() = state.offset();
 }
@@ -7,21 +7,31 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::Paragraph;
 
 use crate::app::{App, Panel};
+use crate::layout::LayoutNode;
 use super::panel_block;
 use super::layout_minimap;
 
+/// Rows of the pane info header above the capture.
+const PANE_HEADER_ROWS: u16 = 2;
+
+/// Capture rows the preview shows in a panel `height` rows tall.
+pub fn capture_rows(height: u16) -> usize {
+    // Less the borders and the header.
+    height.saturating_sub(2 + PANE_HEADER_ROWS).max(1) as usize
+}
+
 pub fn draw_preview(frame: &mut Frame, app: &App, area: Rect) {
     let focused = app.focused == Panel::Preview;
-    let block = panel_block("[4] Preview", focused);
+    let mut title = "[4] Preview".to_string();
+    let inner = panel_block(&title, focused).inner(area);
 
     // When Panes panel is focused, try rendering the layout minimap
     if app.focused == Panel::Panes {
         // Render the block first, then draw minimap in the inner area
-        let inner = block.inner(area);
-        frame.render_widget(block, area);
+        frame.render_widget(panel_block(&title, focused), area);
         if !layout_minimap::draw_layout_minimap(frame, app, inner) {
             // Fallback: minimap couldn't fit, render pane capture over the inner area
-            let content = render_pane_with_header(app);
+            let (content, _) = render_pane_with_header(app, inner.height as usize);
             let fallback = Paragraph::new(content);
             frame.render_widget(fallback, inner);
         }
@@ -31,10 +41,16 @@ pub fn draw_preview(frame: &mut Frame, app: &App, area: Rect) {
     let content = match app.focused {
         Panel::Sessions => render_session_details(app),
         Panel::Windows => render_window_details(app),
-        _ => render_pane_with_header(app),
+        _ => {
+            let (content, position) = render_pane_with_header(app, inner.height as usize);
+            if let Some(position) = position {
+                title = format!("{} {}", title, position);
+            }
+            content
+        }
     };
 
-    let widget = Paragraph::new(content).block(block);
+    let widget = Paragraph::new(content).block(panel_block(&title, focused));
     frame.render_widget(widget, area);
 }
 
@@ -119,10 +135,10 @@ fn days_to_ymd(days: u64) -> (u64, u64, u64) {
 }
 
 fn shorten_path(path: &str) -> String {
-    if let Some(home) = std::env::var("HOME").ok() {
-        if path.starts_with(&home) {
-            return format!("~{}", &path[home.len()..]);
-        }
+    if let Ok(home) = std::env::var("HOME")
+        && path.starts_with(&home)
+    {
+        return format!("~{}", &path[home.len()..]);
     }
     path.to_string()
 }
@@ -142,6 +158,7 @@ fn render_session_details(app: &App) -> Vec<Line<'static>> {
             let mut lines = vec![
                 section_header(format!("  {}", session.name)),
                 Line::from(""),
+                label_value("  Server    ", session.server.label()),
                 label_value("  ID        ", session.id.clone()),
                 label_value("  Windows   ", session.windows.to_string()),
                 Line::from(vec![
@@ -196,7 +213,6 @@ fn render_window_details(app: &App) -> Vec<Line<'static>> {
                 window.flags.trim().to_string()
             };
 
-            // Parse layout type from the layout string (e.g., "ab12,80x24,0,0,0" → extract dimensions)
             let layout_short = simplify_layout(&window.layout);
 
             let mut lines = vec![
@@ -248,33 +264,38 @@ fn render_pane_info_header(app: &App) -> Vec<Line<'static>> {
     match app.selected_pane() {
         Some(pane) => {
             let short_cwd = shorten_path(&pane.cwd);
-            vec![
-                Line::from(vec![
-                    Span::styled(
-                        format!(" {} ", pane.command),
-                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
-                    Span::styled(short_cwd, Style::default().fg(Color::White)),
-                    Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
-                    Span::styled(
-                        format!("{}x{}", pane.width, pane.height),
-                        Style::default().fg(Color::White),
-                    ),
-                    Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
-                    Span::styled(
-                        format!("PID {}", pane.pid),
-                        Style::default().fg(Color::DarkGray),
-                    ),
-                ]),
-                separator_line(),
-            ]
+            let mut spans = vec![
+                Span::styled(
+                    format!(" {} ", pane.command),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                Span::styled(short_cwd, Style::default().fg(Color::White)),
+                Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{}x{}", pane.width, pane.height),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("PID {}", pane.pid),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ];
+            if !pane.title.is_empty() {
+                spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+                spans.push(Span::styled(pane.title, Style::default().fg(Color::DarkGray)));
+            }
+            vec![Line::from(spans), separator_line()]
         }
         None => Vec::new(),
     }
 }
 
-fn render_pane_with_header(app: &App) -> Vec<Line<'static>> {
+/// The pane header and the capture rows that fit in `height`, scrolled back
+/// as far as the preview is, with the lines shown out of how many, e.g.
+/// "81-120/1000".
+fn render_pane_with_header(app: &App, height: usize) -> (Vec<Line<'static>>, Option<String>) {
     let mut lines = render_pane_info_header(app);
 
     if app.pane_capture.is_empty() {
@@ -290,21 +311,23 @@ fn render_pane_with_header(app: &App) -> Vec<Line<'static>> {
             )));
         }
     } else {
-        for l in app.pane_capture.lines() {
-            lines.push(Line::from(l.to_string()));
-        }
+        let rows = height.saturating_sub(lines.len()).max(1);
+        let capture = app.preview_lines();
+        let total = capture.len();
+        let scroll = app.preview_scroll.min(total.saturating_sub(rows));
+        let top = total.saturating_sub(rows + scroll);
+        lines.extend(capture.iter().skip(top).take(rows).cloned());
+        let position = format!("{}-{}/{}", (top + 1).min(total), (top + rows).min(total), total);
+        return (lines, Some(position));
     }
 
-    lines
+    (lines, None)
 }
 
+/// Window size and split shape, e.g. "120x30 {%2 [%3 %4]}".
 fn simplify_layout(layout: &str) -> String {
-    // tmux layout strings look like "ab12,80x24,0,0,0" or more complex nested forms
-    // Extract the dimensions portion if present
-    if let Some((_checksum, rest)) = layout.split_once(',') {
-        if let Some((dims, _)) = rest.split_once(',') {
-            return dims.to_string();
-        }
+    match LayoutNode::parse(layout) {
+        Ok(node) => format!("{}x{} {}", node.width, node.height, node.shape()),
+        Err(_) => layout.to_string(),
     }
-    layout.to_string()
 }
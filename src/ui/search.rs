@@ -0,0 +1,119 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, List, Paragraph};
+
+use crate::app::App;
+use crate::fuzzy;
+use super::{centered_rect, highlight_matches};
+
+pub fn draw_search(frame: &mut Frame, app: &mut App) {
+    let area = centered_rect(80, 70, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let scope = match &app.search.session {
+        Some((_, name)) => format!("session '{}'", name),
+        None => "all panes".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Search scrollback: {} ", scope))
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}_", app.search.query)),
+        ])),
+        rows[0],
+    );
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "Enter:search, then jump  Up/Down:move  Tab:all panes/this session  Esc:close",
+            Style::default().fg(Color::DarkGray),
+        ))),
+        rows[2],
+    );
+
+    let Some(query) = app.search.searched.clone() else {
+        return;
+    };
+    if app.search.hits.is_empty() {
+        let text = if app.search.running() { "(searching)" } else { "(no matches)" };
+        let msg = Paragraph::new(Line::from(Span::styled(text, Style::default().fg(Color::DarkGray))));
+        frame.render_widget(msg, rows[1]);
+        return;
+    }
+
+    let split = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[1]);
+
+    let items: Vec<Line> = app
+        .search
+        .hits
+        .iter()
+        .map(|hit| {
+            let e = &hit.target.entry;
+            let text = hit.text().trim();
+            let matched = fuzzy::fuzzy_match(&query, text).unwrap_or_default();
+            let mut spans = vec![Span::styled(
+                format!("{}:{}.{} ", e.session, e.window_index, e.pane.index),
+                Style::default().fg(Color::Cyan),
+            )];
+            spans.extend(highlight_matches(text, &matched));
+            Line::from(spans)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    frame.render_stateful_widget(list, split[0], &mut app.search.state);
+
+    // Context: the matched line among its neighbours, numbered by scrollback line.
+    let context_block = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let context_inner = context_block.inner(split[1]);
+    frame.render_widget(context_block, split[1]);
+
+    let Some(hit) = app.search.state.selected().and_then(|row| app.search.hits.get(row)) else {
+        return;
+    };
+    let first = hit.line - hit.context_line;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{}  {}", hit.target.label(), hit.target.server.label()),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+    ];
+    for (i, text) in hit.context.iter().enumerate() {
+        let number = Span::styled(format!("{:>6} ", first + i + 1), Style::default().fg(Color::DarkGray));
+        let line = if i == hit.context_line {
+            Line::from(vec![number, Span::styled(text.clone(), Style::default().add_modifier(Modifier::BOLD))])
+                .style(Style::default().bg(Color::DarkGray))
+        } else {
+            Line::from(vec![number, Span::raw(text.clone())])
+        };
+        lines.push(line);
+    }
+    frame.render_widget(Paragraph::new(lines), context_inner);
+}
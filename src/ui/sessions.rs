@@ -5,11 +5,12 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::List;
 
 use crate::app::{App, Panel};
-use super::panel_block;
+use super::{filtered_state, highlight_matches, list_title, panel_block};
 
 pub fn draw_sessions(frame: &mut Frame, app: &mut App, area: Rect) {
     let focused = app.focused == Panel::Sessions;
-    let block = panel_block("[1] Sessions", focused);
+    let title = list_title(app, Panel::Sessions, "[1] Sessions");
+    let block = panel_block(&title, focused);
 
     if app.sessions.is_empty() {
         let list = List::new(vec![Line::from(Span::styled(
@@ -20,15 +21,38 @@ pub fn draw_sessions(frame: &mut Frame, app: &mut App, area: Rect) {
         frame.render_widget(list, area);
         return;
     }
+    let visible = app.visible_items(&Panel::Sessions);
+    if visible.is_empty() {
+        let list = List::new(vec![Line::from(Span::styled(
+            "(no matches)",
+            Style::default().fg(Color::DarkGray),
+        ))])
+        .block(block);
+        frame.render_widget(list, area);
+        return;
+    }
 
-    let items: Vec<Line> = app
-        .sessions
-        .iter()
-        .map(|s| {
-            let attached = if s.attached { " *" } else { "" };
-            Line::from(format!("{} [{}w]{}", s.name, s.windows, attached))
-        })
-        .collect();
+    // With several servers, sessions are grouped under a header per server.
+    // Headers and the filter shift the rows, so the selection is mapped to
+    // display rows.
+    let grouped = app.servers.len() > 1;
+    let mut items: Vec<Line> = Vec::new();
+    let mut rows = Vec::new();
+    for (n, (i, matched)) in visible.iter().enumerate() {
+        let s = &app.sessions[*i];
+        if grouped && (n == 0 || app.sessions[visible[n - 1].0].server != s.server) {
+            items.push(Line::from(Span::styled(
+                format!("── {} ──", s.server.label()),
+                Style::default().fg(Color::DarkGray),
+            )));
+            rows.push(None);
+        }
+        rows.push(Some(*i));
+        let attached = if s.attached { " *" } else { "" };
+        let mut spans = highlight_matches(&s.name, matched);
+        spans.push(Span::raw(format!(" [{}w]{}", s.windows, attached)));
+        items.push(Line::from(spans));
+    }
 
     let highlight = if focused {
         Style::default()
@@ -48,5 +72,7 @@ pub fn draw_sessions(frame: &mut Frame, app: &mut App, area: Rect) {
         list = list.style(Style::default().fg(Color::DarkGray));
     }
 
-    frame.render_stateful_widget(list, area, &mut app.session_state);
+    let mut state = filtered_state(&app.session_state, &rows);
+    frame.render_stateful_widget(list, area, &mut state);
+    *app.session_state.offset_mut() = state.offset();
 }
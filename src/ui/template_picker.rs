@@ -1,10 +1,15 @@
+use std::collections::BTreeMap;
+
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, List, Paragraph};
 
 use crate::app::App;
+use crate::template::{self, SessionTemplate, TemplateFile};
+use crate::tmux::TmuxBackend;
+use super::centered_rect;
 
 pub fn draw_template_picker(frame: &mut Frame, app: &mut App) {
     let area = centered_rect(60, 70, frame.area());
@@ -13,15 +18,28 @@ pub fn draw_template_picker(frame: &mut Frame, app: &mut App) {
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .title(" Templates (Enter:launch  d:delete  Esc:close) ")
+        .title(" Templates ")
         .border_style(Style::default().fg(Color::Cyan));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    let (inner, hints) = (rows[0], rows[1]);
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "Enter:launch  a/A:apply  p:plan  J/K:scroll  i:import  x:export  d:delete  Esc:close",
+            Style::default().fg(Color::DarkGray),
+        ))),
+        hints,
+    );
+
     if app.templates.is_empty() {
         let msg = Paragraph::new(Line::from(Span::styled(
-            "(no templates — press S on a session to save one)",
+            "(no templates — press S on a session to save one, or i to import)",
             Style::default().fg(Color::DarkGray),
         )));
         frame.render_widget(msg, inner);
@@ -38,18 +56,23 @@ pub fn draw_template_picker(frame: &mut Frame, app: &mut App) {
     let items: Vec<Line> = app
         .templates
         .iter()
-        .map(|t| {
-            if t.template.description.is_empty() {
-                Line::from(t.template.name.clone())
-            } else {
-                Line::from(vec![
-                    Span::raw(&t.template.name),
-                    Span::styled(
-                        format!(" — {}", t.template.description),
-                        Style::default().fg(Color::DarkGray),
-                    ),
-                ])
+        .map(|file| {
+            let mut spans = vec![Span::raw(file.name())];
+            if file.project.is_some() {
+                spans.push(Span::styled(" project", Style::default().fg(Color::Magenta)));
+            }
+            if file.is_broken() {
+                spans.push(Span::styled(" ✗", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+            }
+            if let Some(t) = &file.template
+                && !t.template.description.is_empty()
+            {
+                spans.push(Span::styled(
+                    format!(" — {}", t.template.description),
+                    Style::default().fg(Color::DarkGray),
+                ));
             }
+            Line::from(spans)
         })
         .collect();
 
@@ -72,51 +95,129 @@ pub fn draw_template_picker(frame: &mut Frame, app: &mut App) {
     let preview_inner = preview_block.inner(split[1]);
     frame.render_widget(preview_block, split[1]);
 
-    if let Some(t) = app.selected_template() {
-        let mut lines = vec![
-            Line::from(Span::styled(
-                format!("Template: {}", t.template.name),
-                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-            )),
-            Line::from(""),
-        ];
-
-        for (i, win) in t.windows.iter().enumerate() {
-            lines.push(Line::from(Span::styled(
-                format!("  Window {}: {}", i, win.name),
-                Style::default().fg(Color::Yellow),
-            )));
-            lines.push(Line::from(format!("    cwd: {}", win.cwd)));
-            lines.push(Line::from(format!("    panes: {}", win.panes.len())));
-            for (pi, pane) in win.panes.iter().enumerate() {
-                lines.push(Line::from(format!(
-                    "      [{}] {:?} — {}",
-                    pi, pane.split, pane.cwd
-                )));
-            }
+    let Some(file) = app.selected_template_file() else {
+        return;
+    };
+    let mut lines = Vec::new();
+    if file.project.is_some() {
+        lines.push(Line::from(Span::styled(
+            format!("From project: {}", file.path.display()),
+            Style::default().fg(Color::Magenta),
+        )));
+        lines.push(Line::from(""));
+    }
+    lines.extend(problem_lines(file));
+    match &file.template {
+        Some(t) if app.template_show_plan => lines.extend(plan_lines(t, app.tmux())),
+        Some(t) => lines.extend(structure_lines(t)),
+        None => {}
+    }
+    // Keep the last line reachable without scrolling past it.
+    let max_scroll = lines.len().saturating_sub(preview_inner.height as usize) as u16;
+    app.template_preview_scroll = app.template_preview_scroll.min(max_scroll);
+    let preview = Paragraph::new(lines).scroll((app.template_preview_scroll, 0));
+    frame.render_widget(preview, preview_inner);
+}
+
+/// What `template check` would report for the file, if anything.
+fn problem_lines(file: &TemplateFile) -> Vec<Line<'static>> {
+    if !file.is_broken() {
+        return Vec::new();
+    }
+    let heading = if file.template.is_some() { "Problems" } else { "Doesn't parse" };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{}: {}", heading, file.path.display()),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for problem in &file.problems {
+        lines.push(Line::from(Span::styled(format!("  {}", problem), Style::default().fg(Color::Red))));
+    }
+    lines.push(Line::from(""));
+    lines
+}
+
+fn structure_lines(t: &SessionTemplate) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Template: {}", t.template.name),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+
+    if !t.template.variables.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  Variables",
+            Style::default().fg(Color::Yellow),
+        )));
+        for (name, default) in &t.template.variables {
+            let value = if default.is_empty() {
+                Span::styled("(asked at launch)", Style::default().fg(Color::DarkGray))
+            } else {
+                Span::raw(default.clone())
+            };
+            lines.push(Line::from(vec![Span::raw(format!("    {} = ", name)), value]));
         }
+        lines.push(Line::from(""));
+    }
 
-        let preview = Paragraph::new(lines);
-        frame.render_widget(preview, preview_inner);
+    for (i, win) in t.windows.iter().enumerate() {
+        lines.push(Line::from(Span::styled(
+            format!("  Window {}: {}", i, win.name),
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Line::from(format!("    cwd: {}", win.cwd)));
+        lines.push(Line::from(format!("    panes: {}", win.panes.len())));
+        for (pi, pane) in win.panes.iter().enumerate() {
+            lines.push(Line::from(format!(
+                "      [{}] {:?} — {}",
+                pi, pane.split, pane.cwd
+            )));
+        }
     }
+    lines
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
-    let vertical = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(area);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(vertical[1])[1]
+/// The tmux commands a launch would run, after anything that would stop it.
+/// Variables without a value stay as `${name}` and aren't checked.
+fn plan_lines(t: &SessionTemplate, tmux: &dyn TmuxBackend) -> Vec<Line<'static>> {
+    let resolved = template::unset_variables(t).is_empty();
+    let t = if resolved {
+        template::resolve_variables(t, &BTreeMap::new()).unwrap_or_else(|_| t.clone())
+    } else {
+        t.clone()
+    };
+    let plan = match template::plan_launch(&t, &t.template.name) {
+        Ok(plan) => plan,
+        Err(e) => return vec![Line::from(Span::styled(e.to_string(), Style::default().fg(Color::Red)))],
+    };
+    let commands = plan.commands();
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Launch plan: {} steps", commands.len()),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    if resolved {
+        let problems = plan.problems(tmux);
+        for problem in &problems {
+            lines.push(Line::from(Span::styled(problem.clone(), Style::default().fg(Color::Red))));
+        }
+        if !problems.is_empty() {
+            lines.push(Line::from(""));
+        }
+    }
+    let width = commands.len().to_string().len();
+    for (i, command) in commands.into_iter().enumerate() {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>width$} ", i + 1, width = width), Style::default().fg(Color::DarkGray)),
+            Span::raw(command),
+        ]));
+    }
+    lines
 }
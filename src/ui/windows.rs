@@ -5,11 +5,12 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::List;
 
 use crate::app::{App, Panel};
-use super::panel_block;
+use super::{filtered_state, highlight_matches, list_title, panel_block};
 
 pub fn draw_windows(frame: &mut Frame, app: &mut App, area: Rect) {
     let focused = app.focused == Panel::Windows;
-    let block = panel_block("[2] Windows", focused);
+    let title = list_title(app, Panel::Windows, "[2] Windows");
+    let block = panel_block(&title, focused);
 
     if app.windows.is_empty() {
         let msg = if app.sessions.is_empty() {
@@ -25,13 +26,26 @@ pub fn draw_windows(frame: &mut Frame, app: &mut App, area: Rect) {
         frame.render_widget(list, area);
         return;
     }
+    let visible = app.visible_items(&Panel::Windows);
+    if visible.is_empty() {
+        let list = List::new(vec![Line::from(Span::styled(
+            "(no matches)",
+            Style::default().fg(Color::DarkGray),
+        ))])
+        .block(block);
+        frame.render_widget(list, area);
+        return;
+    }
 
-    let items: Vec<Line> = app
-        .windows
+    let items: Vec<Line> = visible
         .iter()
-        .map(|w| {
+        .map(|(i, matched)| {
+            let w = &app.windows[*i];
             let active = if w.active { " *" } else { "" };
-            Line::from(format!("{}:{}{}", w.index, w.name, active))
+            let mut spans = vec![Span::raw(format!("{}:", w.index))];
+            spans.extend(highlight_matches(&w.name, matched));
+            spans.push(Span::raw(active));
+            Line::from(spans)
         })
         .collect();
 
@@ -53,5 +67,9 @@ pub fn draw_windows(frame: &mut Frame, app: &mut App, area: Rect) {
         list = list.style(Style::default().fg(Color::DarkGray));
     }
 
-    frame.render_stateful_widget(list, area, &mut app.window_state);
+    // The filter shifts the rows, so the selection is mapped to display rows.
+    let rows: Vec<_> = visible.iter().map(|(i, _)| Some(*i)).collect();
+    let mut state = filtered_state(&app.window_state, &rows);
+    frame.render_stateful_widget(list, area, &mut state);
+    *app.window_state.offset_mut() = state.offset();
 }